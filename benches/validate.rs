@@ -0,0 +1,51 @@
+//! Times `Png::validate` on a PNG with thousands of IDAT chunks. Run it with and without the
+//! parallel feature to compare:
+//!
+//! ```text
+//! cargo bench --bench validate
+//! cargo bench --bench validate --features parallel
+//! ```
+
+use std::hint::black_box;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use pngme::{validate, Chunk, ChunkType, Png};
+
+const IDAT_CHUNKS: usize = 4096;
+const IDAT_BYTES: usize = 64 * 1024;
+const RUNS: u32 = 10;
+
+fn main() {
+    let mut chunks = vec![Chunk::new(
+        ChunkType::from_str("IHDR").unwrap(),
+        vec![0; 13],
+    )];
+    let idat = ChunkType::from_str("IDAT").unwrap();
+    chunks.extend((0..IDAT_CHUNKS).map(|i| Chunk::new(idat.clone(), vec![i as u8; IDAT_BYTES])));
+    chunks.push(Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()));
+    let png = Png::from_chunks(chunks);
+    let bytes = png.as_bytes();
+
+    report("Png::validate", || black_box(png.validate()));
+    report("validate_bytes", || {
+        black_box(validate::validate_bytes(&bytes))
+    });
+}
+
+/// Prints the mean time of a few runs, after one run to warm up
+fn report<T>(name: &str, mut run: impl FnMut() -> T) {
+    run();
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        run();
+        total += start.elapsed();
+    }
+    println!(
+        "{:<16} {:>4} MiB  {:?} per run",
+        name,
+        IDAT_CHUNKS * IDAT_BYTES / (1024 * 1024),
+        total / RUNS
+    );
+}
@@ -0,0 +1,108 @@
+#ifndef PNGME_H
+#define PNGME_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Outcome of a call through the C interface
+ */
+typedef enum PngmeStatus {
+  /**
+   * The call succeeded
+   */
+  PNGME_STATUS_OK = 0,
+  /**
+   * A pointer was null, or a chunk type was not valid UTF-8
+   */
+  PNGME_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * The PNG has no chunk of the requested type
+   */
+  PNGME_STATUS_NOT_FOUND = 2,
+  /**
+   * Anything else, see [`pngme_last_error`]
+   */
+  PNGME_STATUS_ERROR = 3,
+} PngmeStatus;
+
+/**
+ * Bytes allocated by this library and owned by the caller
+ */
+typedef struct PngmeBuffer {
+  uint8_t *data;
+  size_t len;
+} PngmeBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Stores a message under `chunk_type` right before IEND, compressed and wrapped in an envelope
+ * as `pngme encode` stores it, and writes the new PNG to `out`.
+ *
+ * # Safety
+ *
+ * `png` and `message` must point to at least `png_len` and `message_len` readable bytes,
+ * `chunk_type` must be a NUL-terminated string and `out` must be writable.
+ */
+enum PngmeStatus pngme_encode(const uint8_t *png,
+                              size_t png_len,
+                              const char *chunk_type,
+                              const uint8_t *message,
+                              size_t message_len,
+                              struct PngmeBuffer *out);
+
+/**
+ * Reads the message stored under `chunk_type` by `pngme_encode` or `pngme encode`, joining it
+ * back together if it was split across several chunks, and writes it to `out`.
+ *
+ * # Safety
+ *
+ * `png` must point to at least `png_len` readable bytes, `chunk_type` must be a NUL-terminated
+ * string and `out` must be writable.
+ */
+enum PngmeStatus pngme_decode(const uint8_t *png,
+                              size_t png_len,
+                              const char *chunk_type,
+                              struct PngmeBuffer *out);
+
+/**
+ * Removes the first chunk of type `chunk_type` and writes the new PNG to `out`.
+ *
+ * # Safety
+ *
+ * `png` must point to at least `png_len` readable bytes, `chunk_type` must be a NUL-terminated
+ * string and `out` must be writable.
+ */
+enum PngmeStatus pngme_remove(const uint8_t *png,
+                              size_t png_len,
+                              const char *chunk_type,
+                              struct PngmeBuffer *out);
+
+/**
+ * Releases a buffer returned by this library. Freeing an empty buffer does nothing.
+ *
+ * # Safety
+ *
+ * `buffer` must have come from this library and must not be freed twice.
+ */
+void pngme_free_buffer(struct PngmeBuffer buffer);
+
+/**
+ * Describes the last error on this thread, or returns null if there was none. The string is
+ * owned by the library and stays valid until the next call on the same thread.
+ */
+const char *pngme_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PNGME_H */
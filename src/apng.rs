@@ -0,0 +1,258 @@
+use crate::borrowed::ChunkRef;
+use crate::error::PngmeError;
+use crate::png::Png;
+use crate::Result;
+
+/// Chunk types that make up an APNG animation. Every fcTL and fdAT carries a sequence number,
+/// and together they have to count up from 0 in file order.
+pub const ANIMATION_CHUNK_TYPES: [&str; 3] = ["acTL", "fcTL", "fdAT"];
+
+const ACTL_LENGTH: usize = 8;
+const FCTL_LENGTH: usize = 26;
+
+/// The animation described by the acTL, fcTL and fdAT chunks of an APNG
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Animation {
+    /// Number of frames, as declared in acTL
+    pub num_frames: u32,
+    /// Number of times to play the animation, 0 for forever
+    pub num_plays: u32,
+    /// The frames, in order
+    pub frames: Vec<Frame>,
+}
+
+/// One frame of an APNG, from its fcTL chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Sequence number of the frame's fcTL chunk
+    pub sequence_number: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    /// Numerator of the frame delay in seconds
+    pub delay_num: u16,
+    /// Denominator of the frame delay in seconds, where 0 means 100
+    pub delay_den: u16,
+    /// What happens to the frame's area before the next frame is drawn
+    pub dispose_op: u8,
+    /// How the frame is drawn over the previous output
+    pub blend_op: u8,
+    /// Index of the frame's fcTL chunk among all chunks
+    pub index: usize,
+    /// Whether the frame's pixels are the IDAT chunks, i.e. the default image is the first frame
+    pub uses_idat: bool,
+    /// Number of IDAT or fdAT chunks holding the frame's pixels
+    pub data_chunks: usize,
+}
+
+impl Png {
+    /// Whether this PNG has an acTL chunk, marking it as animated
+    pub fn is_animated(&self) -> bool {
+        self.chunk_by_type("acTL").is_some()
+    }
+
+    /// The animation of an APNG, or `None` for a still image. Fails with
+    /// [`PngmeError::InvalidAnimation`] if the animation chunks are malformed, out of place or out
+    /// of sequence.
+    pub fn animation(&self) -> Result<Option<Animation>> {
+        let chunks: Vec<ChunkRef> = self.chunks().iter().map(ChunkRef::from).collect();
+        read_animation(&chunks)
+    }
+}
+
+/// Reads the animation of an APNG from its chunks, see [`Png::animation`]
+pub fn read_animation(chunks: &[ChunkRef]) -> Result<Option<Animation>> {
+    let invalid = |reason: String| Err(PngmeError::InvalidAnimation(reason));
+    let mut animation: Option<Animation> = None;
+    let mut next_sequence = 0;
+    let mut seen_idat = false;
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        let data = chunk.data();
+        let chunk_type = chunk.chunk_type().to_string();
+        match chunk_type.as_str() {
+            "acTL" if animation.is_some() => {
+                return invalid(format!("second acTL chunk #{}", index))
+            }
+            "acTL" if seen_idat => return invalid(format!("acTL chunk #{} after IDAT", index)),
+            "acTL" if data.len() != ACTL_LENGTH => {
+                return invalid(format!("acTL chunk #{} is {} bytes", index, data.len()))
+            }
+            "acTL" => {
+                animation = Some(Animation {
+                    num_frames: read_u32(data, 0),
+                    num_plays: read_u32(data, 4),
+                    frames: Vec::new(),
+                })
+            }
+            "fcTL" | "fdAT" => {
+                let Some(animation) = &mut animation else {
+                    continue;
+                };
+                let min_length = if chunk_type == "fcTL" { FCTL_LENGTH } else { 4 };
+                if data.len() < min_length {
+                    return invalid(format!(
+                        "{} chunk #{} is {} bytes",
+                        chunk_type,
+                        index,
+                        data.len()
+                    ));
+                }
+                let sequence_number = read_u32(data, 0);
+                if sequence_number != next_sequence {
+                    return invalid(format!(
+                        "{} chunk #{} has sequence number {}, expected {}",
+                        chunk_type, index, sequence_number, next_sequence
+                    ));
+                }
+                next_sequence += 1;
+
+                if chunk_type == "fcTL" {
+                    animation.frames.push(Frame {
+                        sequence_number,
+                        width: read_u32(data, 4),
+                        height: read_u32(data, 8),
+                        x_offset: read_u32(data, 12),
+                        y_offset: read_u32(data, 16),
+                        delay_num: read_u16(data, 20),
+                        delay_den: read_u16(data, 22),
+                        dispose_op: data[24],
+                        blend_op: data[25],
+                        index,
+                        uses_idat: !seen_idat,
+                        data_chunks: 0,
+                    });
+                    continue;
+                }
+                match animation.frames.last_mut() {
+                    Some(frame) if !frame.uses_idat => frame.data_chunks += 1,
+                    _ => {
+                        return invalid(format!(
+                            "fdAT chunk #{} does not follow an fcTL after the image data",
+                            index
+                        ))
+                    }
+                }
+            }
+            "IDAT" => {
+                seen_idat = true;
+                if let Some(frame) = animation.as_mut().and_then(|a| a.frames.last_mut()) {
+                    if frame.uses_idat {
+                        frame.data_chunks += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(animation) = &animation {
+        if animation.frames.len() != animation.num_frames as usize {
+            return invalid(format!(
+                "acTL declares {} frame(s) but there are {}",
+                animation.num_frames,
+                animation.frames.len()
+            ));
+        }
+    }
+    Ok(animation)
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn read_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes(data[at..at + 2].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+    }
+
+    fn frame_control(sequence_number: u32) -> Chunk {
+        let mut data = sequence_number.to_be_bytes().to_vec();
+        for value in [16u32, 8, 0, 0] {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data.extend_from_slice(&[0, 1, 0, 10, 0, 0]);
+        chunk("fcTL", data)
+    }
+
+    fn frame_data(sequence_number: u32) -> Chunk {
+        let mut data = sequence_number.to_be_bytes().to_vec();
+        data.extend_from_slice(b"pixels");
+        chunk("fdAT", data)
+    }
+
+    fn testing_apng() -> Png {
+        let mut control = 2u32.to_be_bytes().to_vec();
+        control.extend_from_slice(&0u32.to_be_bytes());
+        Png::from_chunks(vec![
+            chunk("IHDR", vec![0; 13]),
+            chunk("acTL", control),
+            frame_control(0),
+            chunk("IDAT", b"pixels".to_vec()),
+            frame_control(1),
+            frame_data(2),
+            frame_data(3),
+            chunk("IEND", Vec::new()),
+        ])
+    }
+
+    #[test]
+    pub fn test_animation() {
+        let animation = testing_apng().animation().unwrap().unwrap();
+
+        assert_eq!(animation.num_frames, 2);
+        assert_eq!(animation.num_plays, 0);
+        assert_eq!(animation.frames.len(), 2);
+        assert!(animation.frames[0].uses_idat);
+        assert_eq!(animation.frames[0].data_chunks, 1);
+        assert_eq!(animation.frames[1].index, 4);
+        assert_eq!(animation.frames[1].data_chunks, 2);
+        assert_eq!(
+            (animation.frames[1].width, animation.frames[1].delay_den),
+            (16, 10)
+        );
+        assert!(testing_apng().is_animated());
+    }
+
+    #[test]
+    pub fn test_still_image() {
+        let mut png = testing_apng();
+        png.remove_chunk("acTL").unwrap();
+
+        assert!(!png.is_animated());
+        assert_eq!(png.animation().unwrap(), None);
+    }
+
+    #[test]
+    pub fn test_broken_animation() {
+        let mut png = testing_apng();
+        png.remove_chunk_at(5).unwrap();
+        assert!(matches!(
+            png.animation(),
+            Err(PngmeError::InvalidAnimation(reason)) if reason.contains("expected 2")
+        ));
+
+        let mut png = testing_apng();
+        png.remove_chunk_at(4).unwrap();
+        assert!(matches!(
+            png.animation(),
+            Err(PngmeError::InvalidAnimation(_))
+        ));
+
+        let mut png = testing_apng();
+        png.encode_bytes("ruSt", b"hidden").unwrap();
+        assert!(png.animation().is_ok());
+    }
+}
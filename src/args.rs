@@ -0,0 +1,739 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::builder::{ArgPredicate, Resettable};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use pngme::segment::DEFAULT_MAX_CHUNK_SIZE;
+use pngme::{ChunkType, InsertPosition};
+
+use crate::config::Config;
+use crate::exit_code;
+
+/// Hide secret messages inside PNG files
+#[derive(Debug, Parser)]
+#[command(name = "pngme", version, about, after_help = exit_code::HELP)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: PngMeArgs,
+    /// Print nothing but errors, for scripts that only check the exit code
+    #[arg(long, short, global = true)]
+    pub quiet: bool,
+    /// Log what pngme is doing to stderr, -vv for more detail
+    #[arg(long, short, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// Skip damaged chunks with a warning instead of failing, resynchronizing on the next intact
+    /// chunk. Applies to commands that read the whole image, such as decode, extract and info
+    #[arg(long, global = true)]
+    pub lenient: bool,
+    /// Output format for print, info, capacity, scan, inspect, diff, decode, ls-payloads and verify
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// Read default settings from this file instead of ~/.config/pngme/config.toml
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+}
+
+/// Subcommands that fall back to the chunk type from the config file when none is given. insert
+/// is left out, since the output path after its chunk type would be mistaken for the chunk type.
+const DEFAULT_CHUNK_TYPE_COMMANDS: [&str; 5] = ["encode", "decode", "remove", "extract", "inspect"];
+
+impl Cli {
+    /// The --config path, picked out of the command line before the rest of it is parsed
+    pub fn config_path() -> Option<PathBuf> {
+        Cli::command()
+            .ignore_errors(true)
+            .disable_help_flag(true)
+            .disable_version_flag(true)
+            .try_get_matches()
+            .ok()?
+            .get_one::<PathBuf>("config")
+            .cloned()
+    }
+
+    /// Parses a command line, taking the settings of the config file as defaults that the options
+    /// given override
+    pub fn try_parse_with<I, T>(args: I, config: &Config) -> Result<Cli, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut command = Cli::command();
+        if let Some(format) = &config.format {
+            command = command.mut_arg("format", |arg| arg.default_value(format.clone()));
+        }
+        let subcommands: Vec<String> = command
+            .get_subcommands()
+            .map(|subcommand| subcommand.get_name().to_string())
+            .collect();
+        for name in subcommands {
+            // mut_arg would move the argument to the end, changing the order of positionals
+            command = command.mut_subcommand(&name, |subcommand| {
+                subcommand.mut_args(|arg| with_default(arg, &name, config))
+            });
+        }
+
+        let mut matches = command.try_get_matches_from_mut(args)?;
+        let typed_chunk_type = matches
+            .subcommand_matches("encode")
+            .and_then(|encode| encode.value_source("chunk_type"))
+            == Some(ValueSource::CommandLine);
+        let mut cli = Cli::from_arg_matches_mut(&mut matches)?;
+        match &mut cli.command {
+            PngMeArgs::Encode(args) => args.password.default_key_file = config.key_file.clone(),
+            PngMeArgs::Decode(args) => args.password.default_key_file = config.key_file.clone(),
+            _ => {}
+        }
+        if let PngMeArgs::Encode(args) = &mut cli.command {
+            // With a default chunk type the arguments after the file may start with the message:
+            // a lone argument is always the message, and of two the first is the message unless
+            // it is a valid chunk type
+            let shifted = typed_chunk_type
+                && !args.auto_type
+                && args.input_file.is_none()
+                && (args.message.is_none()
+                    || (args.output.is_none() && ChunkType::from_str(&args.chunk_type).is_err()));
+            if let (Some(chunk_type), true) = (&config.chunk_type, shifted) {
+                args.output = args.message.take().map(PathBuf::from);
+                args.message = Some(std::mem::replace(&mut args.chunk_type, chunk_type.clone()));
+            }
+            if args.message.is_none() && args.input_file.is_none() {
+                let encode = command.find_subcommand_mut("encode").unwrap();
+                return Err(encode.error(
+                    ErrorKind::MissingRequiredArgument,
+                    "either a <MESSAGE> or --input-file is required",
+                ));
+            }
+        }
+        Ok(cli)
+    }
+}
+
+/// Makes the setting of the config file that goes with an argument of a subcommand its default
+fn with_default(arg: Arg, subcommand: &str, config: &Config) -> Arg {
+    match arg.get_id().as_str() {
+        "chunk_type" if DEFAULT_CHUNK_TYPE_COMMANDS.contains(&subcommand) => {
+            match &config.chunk_type {
+                Some(chunk_type) => arg
+                    .default_value(chunk_type.clone())
+                    .required(false)
+                    .required_unless_present(Resettable::Reset),
+                None => arg,
+            }
+        }
+        // A lone argument after the file is the message once the chunk type can be left out,
+        // and only after parsing is it clear which was given
+        "message" if config.chunk_type.is_some() => arg.required_unless_present(Resettable::Reset),
+        "compress" if subcommand == "encode" && config.compress == Some(true) => arg
+            .default_value("true")
+            .default_value_if("no_compress", ArgPredicate::IsPresent, "false"),
+        "backup" if config.backup == Some(true) => arg.default_value("true").default_value_if(
+            "no_backup",
+            ArgPredicate::IsPresent,
+            "false",
+        ),
+        _ => arg,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    Text,
+    /// A single JSON document on stdout
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TextEncoding {
+    /// The message as text
+    Utf8,
+    /// Standard base64 with padding
+    Base64,
+    /// Two lowercase hex digits per byte
+    Hex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EmbedMode {
+    /// In an ancillary chunk of its own
+    Chunk,
+    /// In the least significant bits of the pixel data, which is much harder to spot
+    Lsb,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PngMeArgs {
+    /// Encode a message into a chunk of a PNG file
+    Encode(EncodeArgs),
+    /// Decode the message stored in a chunk of a PNG file
+    Decode(DecodeArgs),
+    /// Remove a chunk from a PNG file
+    Remove(RemoveArgs),
+    /// Insert the bytes of a file as a chunk, verbatim
+    Insert(InsertArgs),
+    /// Write the raw data of a chunk to a file
+    Extract(ExtractArgs),
+    /// Print the header fields of a chunk and a hexdump of its data
+    Inspect(InspectArgs),
+    /// List the payloads stored by name with encode --name
+    LsPayloads(LsPayloadsArgs),
+    /// Remove every ancillary chunk, including embedded messages, before publishing an image
+    Strip(StripArgs),
+    /// Print every chunk of a PNG file
+    Print(PrintArgs),
+    /// Print the image dimensions and format from the IHDR chunk
+    Info(InfoArgs),
+    /// Report how many bytes each embedding mode can hold
+    Capacity(CapacityArgs),
+    /// Read or write tEXt, zTXt and iTXt metadata
+    Text(TextArgs),
+    /// List every chunk with its offset, CRC status and property flags
+    Scan(ScanArgs),
+    /// List the chunks added, removed or modified between two PNG files
+    Diff(DiffArgs),
+    /// Check the signature, every chunk CRC and the placement of IHDR and IEND
+    Verify(VerifyArgs),
+    /// Recompute bad CRCs and optionally drop unreadable trailing data
+    Repair(RepairArgs),
+    /// Print a shell completion script, or write it into a directory
+    Completions(CompletionsArgs),
+    /// Print the man page, or write pages for pngme and every subcommand into a directory
+    Manpage(ManpageArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct EncodeArgs {
+    /// PNG file to encode the message into, or - for stdin
+    pub file_path: PathBuf,
+    /// Four letter chunk type to store the message under
+    pub chunk_type: String,
+    /// Fix the case of the chunk type's letters so it is ancillary, private and safe to copy,
+    /// e.g. rust becomes ruSt
+    #[arg(long)]
+    pub auto_type: bool,
+    /// Message to encode
+    #[arg(required_unless_present = "input_file", conflicts_with = "input_file")]
+    pub message: Option<String>,
+    /// How the message argument spells the bytes to embed
+    #[arg(long, value_enum, default_value_t = TextEncoding::Utf8, conflicts_with = "input_file")]
+    pub encoding: TextEncoding,
+    /// Where to write the result, or - for stdout. Defaults to overwriting the input file
+    #[arg(conflicts_with = "recursive")]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub password: PasswordArgs,
+    /// Compress the message with zlib before embedding it
+    #[arg(long, overrides_with = "no_compress")]
+    pub compress: bool,
+    /// Embed the message uncompressed, even if the config file asks for compression
+    #[arg(long, overrides_with = "compress")]
+    pub no_compress: bool,
+    /// Embed the raw bytes of this file (or - for stdin) instead of a message
+    #[arg(long)]
+    pub input_file: Option<PathBuf>,
+    /// Split payloads larger than this many bytes across several chunks, at most 2147483647 as
+    /// PNG allows
+    #[arg(long, default_value_t = DEFAULT_MAX_CHUNK_SIZE)]
+    pub max_chunk_size: usize,
+    /// Where to hide the message
+    #[arg(long, value_enum, default_value_t = EmbedMode::Chunk)]
+    pub mode: EmbedMode,
+    /// Sign the message with this Ed25519 private key (PKCS#8 PEM) and store the signature in a
+    /// siGn chunk
+    #[arg(long, value_name = "PEM")]
+    pub sign_key: Option<PathBuf>,
+    #[command(flatten)]
+    pub hmac_key: HmacKeyArgs,
+    /// Store the payload under this name in the image's manifest, replacing any earlier payload
+    /// with the same name, so several payloads can share an image
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Store the payload bare, without the envelope that records how it was compressed and
+    /// encrypted
+    #[arg(long)]
+    pub raw: bool,
+    /// Copy the file being overwritten to <file>.bak first
+    #[arg(long, overrides_with = "no_backup")]
+    pub backup: bool,
+    /// Do not copy the file first, even if the config file asks for backups
+    #[arg(long, overrides_with = "backup")]
+    pub no_backup: bool,
+    /// Report what would change without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub position: PositionArgs,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+}
+
+#[derive(Debug, Args)]
+#[group(multiple = false)]
+pub struct PasswordArgs {
+    /// Encrypt or decrypt the payload with a key derived from this passphrase. Other users can
+    /// see it in the process list, so prefer one of the options below
+    #[arg(long)]
+    pub password: Option<String>,
+    /// Read the passphrase from this file, without its trailing newline
+    #[arg(long, value_name = "PATH")]
+    pub key_file: Option<PathBuf>,
+    /// Read the passphrase from this environment variable, e.g. PNGME_PASSWORD
+    #[arg(long, value_name = "VAR")]
+    pub password_env: Option<String>,
+    /// Prompt for the passphrase on the terminal without echoing it
+    #[arg(long)]
+    pub ask_password: bool,
+    /// Key file named by the config file, used when none of the options above is given
+    #[arg(skip)]
+    pub default_key_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+#[group(multiple = false)]
+pub struct HmacKeyArgs {
+    /// Append an HMAC-SHA256 keyed by this passphrase on encode, and check and strip it on decode,
+    /// failing with exit code 5 if the payload was tampered with. Other users can see it in the
+    /// process list, so prefer one of the options below
+    #[arg(long, value_name = "PASSPHRASE")]
+    pub hmac_key: Option<String>,
+    /// Read the HMAC passphrase from this file, without its trailing newline
+    #[arg(long, value_name = "PATH")]
+    pub hmac_key_file: Option<PathBuf>,
+    /// Read the HMAC passphrase from this environment variable, e.g. PNGME_HMAC_KEY
+    #[arg(long, value_name = "VAR")]
+    pub hmac_key_env: Option<String>,
+    /// Prompt for the HMAC passphrase on the terminal without echoing it
+    #[arg(long)]
+    pub ask_hmac_key: bool,
+}
+
+#[derive(Debug, Args)]
+#[group(multiple = false)]
+pub struct PositionArgs {
+    /// Place the new chunk right before IEND, where strict decoders still see it (the default)
+    #[arg(long)]
+    pub before_iend: bool,
+    /// Place the new chunk right after IHDR
+    #[arg(long)]
+    pub after_ihdr: bool,
+    /// Place the new chunk at this index in the chunk list
+    #[arg(long, value_name = "N")]
+    pub at_index: Option<usize>,
+}
+
+impl PositionArgs {
+    pub fn position(&self) -> InsertPosition {
+        match (self.after_ihdr, self.at_index) {
+            (true, _) => InsertPosition::AfterIhdr,
+            (false, Some(index)) => InsertPosition::AtIndex(index),
+            (false, None) => InsertPosition::BeforeIend,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct BatchArgs {
+    /// Treat the file as a directory to search recursively or a quoted glob pattern, and apply
+    /// the operation in place to every PNG file found
+    #[arg(long, short)]
+    pub recursive: bool,
+    /// Number of files to process in parallel with --recursive
+    #[arg(long, short, default_value_t = 1, requires = "recursive")]
+    pub jobs: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeArgs {
+    /// PNG file to decode the message from, or - for stdin
+    pub file_path: PathBuf,
+    /// Four letter chunk type the message is stored under
+    #[arg(required_unless_present = "name", conflicts_with = "name")]
+    pub chunk_type: Option<String>,
+    /// Decode the payload stored under this name instead, see ls-payloads
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Fix the case of the chunk type's letters the way encode --auto-type does
+    #[arg(long, conflicts_with = "name")]
+    pub auto_type: bool,
+    #[command(flatten)]
+    pub password: PasswordArgs,
+    /// Write the raw decoded bytes to this file (or - for stdout) instead of printing them
+    #[arg(long)]
+    pub output_file: Option<PathBuf>,
+    /// How to print the message, base64 and hex being safe for binary payloads
+    #[arg(long, value_enum, default_value_t = TextEncoding::Utf8, conflicts_with = "output_file")]
+    pub encoding: TextEncoding,
+    /// Where the message was hidden
+    #[arg(long, value_enum, default_value_t = EmbedMode::Chunk)]
+    pub mode: EmbedMode,
+    /// Refuse to output the message unless it was signed by this Ed25519 public key (PEM)
+    #[arg(long, value_name = "PEM")]
+    pub verify_key: Option<PathBuf>,
+    #[command(flatten)]
+    pub hmac_key: HmacKeyArgs,
+    /// Read a payload stored with encode --raw, without looking for an envelope
+    #[arg(long)]
+    pub raw: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RemoveArgs {
+    /// PNG file to remove the chunk from, or - to read stdin and write stdout
+    pub file_path: PathBuf,
+    /// Four letter chunk type to remove
+    pub chunk_type: String,
+    /// Remove every chunk of this type instead of only the first
+    #[arg(long, conflicts_with = "index")]
+    pub all: bool,
+    /// Remove the Nth chunk of this type, counting from 0, instead of the first
+    #[arg(long, value_name = "N")]
+    pub index: Option<usize>,
+    /// Copy the file to <file>.bak before changing it
+    #[arg(long, overrides_with = "no_backup")]
+    pub backup: bool,
+    /// Do not copy the file first, even if the config file asks for backups
+    #[arg(long, overrides_with = "backup")]
+    pub no_backup: bool,
+    /// Report what would change without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct InsertArgs {
+    /// PNG file to insert the chunk into, or - for stdin
+    pub file_path: PathBuf,
+    /// Four letter chunk type to give the new chunk
+    pub chunk_type: String,
+    /// Where to write the result, or - for stdout. Defaults to overwriting the input file
+    pub output: Option<PathBuf>,
+    /// File (or - for stdin) whose bytes become the chunk data, without compression, encryption
+    /// or splitting
+    #[arg(long, value_name = "PATH")]
+    pub data_file: PathBuf,
+    /// Copy the file being overwritten to <file>.bak first
+    #[arg(long, overrides_with = "no_backup")]
+    pub backup: bool,
+    /// Do not copy the file first, even if the config file asks for backups
+    #[arg(long, overrides_with = "backup")]
+    pub no_backup: bool,
+    /// Report what would change without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub position: PositionArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ExtractArgs {
+    /// PNG file to extract the chunk from, or - for stdin
+    pub file_path: PathBuf,
+    /// Four letter chunk type to extract, e.g. iCCP or eXIf
+    pub chunk_type: String,
+    /// Extract the Nth chunk of this type, counting from 0, instead of the first
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub index: usize,
+    /// File to write the chunk data to, or - for stdout
+    #[arg(long, short)]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct InspectArgs {
+    /// PNG file to inspect, or - for stdin
+    pub file_path: PathBuf,
+    /// Four letter chunk type to inspect
+    pub chunk_type: String,
+    /// Inspect the Nth chunk of this type, counting from 0, instead of the first
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub index: usize,
+    /// Only dump the first N bytes of the data
+    #[arg(long, value_name = "N")]
+    pub bytes: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct LsPayloadsArgs {
+    /// PNG file to list the payloads of, or - for stdin
+    pub file_path: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct StripArgs {
+    /// PNG file to strip, or - to read stdin and write stdout
+    pub file_path: PathBuf,
+    /// Where to write the result, or - for stdout. Defaults to overwriting the input file
+    #[arg(conflicts_with = "recursive")]
+    pub output: Option<PathBuf>,
+    /// Comma-separated ancillary chunk types to keep, e.g. tEXt,pHYs
+    #[arg(long, value_delimiter = ',', value_name = "TYPES")]
+    pub keep: Vec<String>,
+    /// Copy the file being overwritten to <file>.bak first
+    #[arg(long, overrides_with = "no_backup")]
+    pub backup: bool,
+    /// Do not copy the file first, even if the config file asks for backups
+    #[arg(long, overrides_with = "backup")]
+    pub no_backup: bool,
+    /// Report what would change without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub batch: BatchArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct PrintArgs {
+    /// PNG file to print, or - for stdin
+    pub file_path: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    /// PNG file to describe, or - for stdin
+    pub file_path: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct CapacityArgs {
+    /// PNG file to measure, or - for stdin
+    pub file_path: PathBuf,
+    /// Also check whether this payload fits, as is and compressed
+    #[arg(long)]
+    pub input_file: Option<PathBuf>,
+    /// Account for the bytes that encrypting with --password adds
+    #[arg(long)]
+    pub encrypted: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ScanArgs {
+    /// PNG file to scan, or - for stdin
+    pub file_path: PathBuf,
+    /// Name and describe every chunk type, and flag private types that are not known
+    #[arg(long)]
+    pub describe: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// The original PNG file, or - for stdin
+    pub before: PathBuf,
+    /// The changed PNG file, or - for stdin
+    pub after: PathBuf,
+    /// Also print a hexdump of the data of every changed chunk, showing only the differing rows
+    /// of modified chunks
+    #[arg(long)]
+    pub hexdump: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// PNG file to verify, or - for stdin
+    pub file_path: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct RepairArgs {
+    /// PNG file to repair, or - for stdin
+    pub file_path: PathBuf,
+    /// Where to write the result, or - for stdout. Defaults to overwriting the input file
+    pub output: Option<PathBuf>,
+    /// Drop any data after the last readable chunk
+    #[arg(long)]
+    pub drop_trailing: bool,
+    /// Report what would change without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// Shell to complete pngme commands in
+    #[arg(value_enum)]
+    pub shell: Shell,
+    /// Write the script into this directory, under the name the shell looks for, instead of
+    /// printing it
+    #[arg(long, value_name = "DIR")]
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ManpageArgs {
+    /// Write pngme.1 and a pngme-<command>.1 page per subcommand into this directory instead of
+    /// printing the pngme.1 page
+    #[arg(long, value_name = "DIR")]
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct TextArgs {
+    #[command(subcommand)]
+    pub command: TextCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TextCommand {
+    /// Print every text entry, or the text stored under one keyword
+    Get(TextGetArgs),
+    /// Store text under a keyword, replacing any existing entry with that keyword
+    Set(TextSetArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct TextGetArgs {
+    /// PNG file to read text from, or - for stdin
+    pub file_path: PathBuf,
+    /// Only print the text stored under this keyword
+    pub keyword: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct TextSetArgs {
+    /// PNG file to write text to, or - to read stdin and write stdout
+    pub file_path: PathBuf,
+    /// Keyword to store the text under, e.g. Title, Author or Comment
+    pub keyword: String,
+    /// Text to store
+    pub value: String,
+    /// Store the text compressed, in a zTXt or compressed iTXt chunk
+    #[arg(long)]
+    pub compress: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str], config: &Config) -> Result<Cli, clap::Error> {
+        Cli::try_parse_with([&["pngme"], args].concat(), config)
+    }
+
+    fn encode(args: &[&str], config: &Config) -> EncodeArgs {
+        match parse(args, config).unwrap().command {
+            PngMeArgs::Encode(args) => args,
+            command => panic!("parsed {:?}", command),
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            chunk_type: Some("waTr".to_string()),
+            compress: Some(true),
+            format: Some("json".to_string()),
+            backup: Some(true),
+            key_file: Some(PathBuf::from("key")),
+        }
+    }
+
+    #[test]
+    pub fn test_config_defaults() {
+        let cli = parse(&["decode", "image.png"], &config()).unwrap();
+        assert_eq!(cli.format, OutputFormat::Json);
+        match cli.command {
+            PngMeArgs::Decode(args) => {
+                assert_eq!(args.chunk_type.as_deref(), Some("waTr"));
+                assert_eq!(args.password.default_key_file, Some(PathBuf::from("key")));
+                assert!(args.password.key_file.is_none());
+            }
+            command => panic!("parsed {:?}", command),
+        }
+
+        let args = encode(&["encode", "image.png", "ruSt", "hello"], &config());
+        assert!(args.compress && args.backup);
+        let args = encode(
+            &["encode", "image.png", "ruSt", "hello"],
+            &Config::default(),
+        );
+        assert!(!args.compress && !args.backup);
+        assert!(args.password.default_key_file.is_none());
+    }
+
+    #[test]
+    pub fn test_command_line_wins() {
+        let cli = parse(
+            &[
+                "--format",
+                "text",
+                "decode",
+                "image.png",
+                "ruSt",
+                "--password",
+                "pw",
+            ],
+            &config(),
+        )
+        .unwrap();
+        assert_eq!(cli.format, OutputFormat::Text);
+        match cli.command {
+            PngMeArgs::Decode(args) => {
+                assert_eq!(args.chunk_type.as_deref(), Some("ruSt"));
+                assert_eq!(args.password.password.as_deref(), Some("pw"));
+            }
+            command => panic!("parsed {:?}", command),
+        }
+
+        let args = encode(
+            &[
+                "encode",
+                "image.png",
+                "ruSt",
+                "hello",
+                "--no-compress",
+                "--no-backup",
+            ],
+            &config(),
+        );
+        assert!(!args.compress && !args.backup);
+    }
+
+    #[test]
+    pub fn test_default_chunk_type_positionals() {
+        let positionals = |args: &EncodeArgs| {
+            (
+                args.chunk_type.clone(),
+                args.message.clone(),
+                args.output.clone(),
+            )
+        };
+        let args = encode(&["encode", "image.png", "build 42"], &config());
+        assert_eq!(
+            positionals(&args),
+            ("waTr".to_string(), Some("build 42".to_string()), None)
+        );
+        let args = encode(&["encode", "image.png", "build 42", "out.png"], &config());
+        assert_eq!(
+            positionals(&args),
+            (
+                "waTr".to_string(),
+                Some("build 42".to_string()),
+                Some(PathBuf::from("out.png"))
+            )
+        );
+        let args = encode(&["encode", "image.png", "ruSt", "hello"], &config());
+        assert_eq!(
+            positionals(&args),
+            ("ruSt".to_string(), Some("hello".to_string()), None)
+        );
+        let args = encode(
+            &["encode", "image.png", "ruSt", "hello", "out.png"],
+            &config(),
+        );
+        assert_eq!(
+            positionals(&args),
+            (
+                "ruSt".to_string(),
+                Some("hello".to_string()),
+                Some(PathBuf::from("out.png"))
+            )
+        );
+
+        assert!(parse(&["encode", "image.png"], &config()).is_err());
+        assert!(parse(&["encode", "image.png", "hello"], &Config::default()).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::png::Png;
+use crate::Result;
+
+impl Png {
+    /// Reads and parses a whole PNG from an async reader, such as an upload body or a
+    /// `tokio::fs::File`, without blocking the executor
+    pub async fn from_async_reader<R: AsyncRead + Unpin>(mut reader: R) -> Result<Png> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Png::try_from(bytes.as_ref())
+    }
+
+    /// Writes the PNG to an async writer and flushes it
+    pub async fn write_async<W: AsyncWrite + Unpin>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(&self.as_bytes()).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::PngmeError;
+    use std::str::FromStr;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hidden".to_vec()),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+    }
+
+    #[tokio::test]
+    pub async fn test_async_round_trip() {
+        let mut written = Vec::new();
+        testing_png().write_async(&mut written).await.unwrap();
+        let png = Png::from_async_reader(written.as_slice()).await.unwrap();
+
+        assert_eq!(written, testing_png().as_bytes());
+        assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), b"hidden");
+    }
+
+    #[tokio::test]
+    pub async fn test_async_invalid_input() {
+        assert!(matches!(
+            Png::from_async_reader(&b"not a png"[..]).await,
+            Err(PngmeError::MissingSignature)
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use pngme::{progress, PngmeError, Result};
+
+use crate::output;
+
+/// Expands a batch target into the PNG files it names.
+///
+/// A glob pattern (quote it so the shell leaves it alone) matches files and directories, a
+/// directory is searched recursively for `.png` files, and anything else is taken as a single file.
+pub fn expand(target: &Path) -> Result<Vec<PathBuf>> {
+    let pattern = target.to_string_lossy();
+    let roots = if pattern.contains(['*', '?', '[']) {
+        glob::glob(&pattern)
+            .map_err(|err| {
+                PngmeError::InvalidArgument(format!("invalid glob pattern {}: {}", pattern, err))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|err| PngmeError::Io(err.into()))?
+    } else {
+        vec![target.to_path_buf()]
+    };
+
+    let mut files = Vec::new();
+    for root in roots {
+        match root.is_dir() {
+            true => find_pngs(&root, &mut files)?,
+            false => files.push(root),
+        }
+    }
+    files.sort();
+    files.dedup();
+    tracing::debug!(pattern = %pattern, files = files.len(), "found PNG files");
+    if files.is_empty() {
+        return Err(PngmeError::InvalidArgument(format!(
+            "no PNG files match {}",
+            pattern
+        )));
+    }
+    Ok(files)
+}
+
+/// Runs an operation on every file using `jobs` worker threads, reporting each file's outcome as
+/// it finishes and failing at the end if any file failed
+pub fn run<F>(files: &[PathBuf], jobs: usize, operation: F) -> Result<()>
+where
+    F: Fn(&Path) -> Result<()> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let failed = Mutex::new(0);
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, files.len()) {
+            scope.spawn(|| {
+                while let Some(file) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    tracing::debug!(file = %file.display(), "processing");
+                    match operation(file) {
+                        Ok(()) => say!("ok: {}", file.display()),
+                        Err(err) => {
+                            output::clear_progress();
+                            eprintln!("failed: {}: {}", file.display(), err);
+                            *failed.lock().unwrap() += 1;
+                        }
+                    }
+                    let finished = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                    progress::report("processing files", finished, files.len());
+                }
+            });
+        }
+    });
+
+    let failed = failed.into_inner().unwrap();
+    say!("{} file(s) processed, {} failed", files.len(), failed);
+    match failed {
+        0 => Ok(()),
+        _ => Err(PngmeError::BatchFailed {
+            failed,
+            total: files.len(),
+        }),
+    }
+}
+
+fn find_pngs(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_pngs(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
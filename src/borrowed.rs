@@ -0,0 +1,248 @@
+use std::fmt::Display;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::PngmeError;
+use crate::png::Png;
+use crate::Result;
+
+/// A chunk borrowed from the bytes of a PNG file, without copying its data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkRef<'a> {
+    chunk_type: [u8; 4],
+    data: &'a [u8],
+    crc: u32,
+}
+
+impl<'a> ChunkRef<'a> {
+    /// Number of bytes in the chunk's data field
+    pub fn length(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    /// The chunk type code of this chunk
+    pub fn chunk_type(&self) -> ChunkType {
+        ChunkType::try_from(self.chunk_type).expect("chunk types are checked when parsing")
+    }
+
+    /// The data bytes of this chunk
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// The CRC of this chunk, as stored in the file
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    /// Checks whether the stored CRC matches the CRC calculated over the chunk type and data
+    pub fn is_crc_valid(&self) -> bool {
+        self.crc == Chunk::compute_crc(&self.chunk_type(), self.data)
+    }
+
+    /// Returns the chunk if its stored CRC is correct, and an [`PngmeError::InvalidCrc`] if not
+    pub fn check_crc(self) -> Result<ChunkRef<'a>> {
+        let computed = Chunk::compute_crc(&self.chunk_type(), self.data);
+        if self.crc != computed {
+            return Err(PngmeError::InvalidCrc {
+                chunk_type: self.chunk_type(),
+                expected: computed,
+                actual: self.crc,
+            });
+        }
+        Ok(self)
+    }
+
+    /// Copies the chunk into an owned [`Chunk`], keeping the stored CRC even if it is wrong
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::from_parts_unchecked(self.chunk_type(), self.data.to_vec(), self.crc)
+    }
+}
+
+impl<'a> From<&'a Chunk> for ChunkRef<'a> {
+    fn from(chunk: &'a Chunk) -> Self {
+        ChunkRef {
+            chunk_type: chunk.chunk_type().bytes(),
+            data: chunk.data(),
+            crc: chunk.crc(),
+        }
+    }
+}
+
+impl Display for ChunkRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Chunk {{")?;
+        writeln!(f, "  Length: {}", self.length())?;
+        writeln!(f, "  Type: {}", self.chunk_type())?;
+        writeln!(f, "  Data: {} bytes", self.data.len())?;
+        writeln!(f, "  Crc: {}", self.crc)?;
+        writeln!(f, "}}")?;
+        Ok(())
+    }
+}
+
+/// A PNG file borrowed as bytes, whose chunks are parsed lazily and without copying.
+///
+/// Only the signature is checked up front. Chunks are read one at a time by [`PngRef::chunks`],
+/// so opening even a very large file costs nothing until its chunks are walked.
+#[derive(Debug, Clone, Copy)]
+pub struct PngRef<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> PngRef<'a> {
+    /// Borrows the bytes of a PNG file, checking the signature
+    pub fn new(bytes: &'a [u8]) -> Result<PngRef<'a>> {
+        if !bytes.starts_with(&Png::STANDARD_HEADER) {
+            return Err(PngmeError::MissingSignature);
+        }
+        Ok(PngRef { bytes })
+    }
+
+    /// The bytes of the whole file
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Iterates over the chunks in file order. Chunks with bad CRCs are yielded as they are, so
+    /// check [`ChunkRef::is_crc_valid`] if it matters. Iteration stops after the first error.
+    pub fn chunks(&self) -> ChunkRefs<'a> {
+        ChunkRefs {
+            bytes: self.bytes,
+            offset: Png::STANDARD_HEADER.len(),
+            done: false,
+        }
+    }
+
+    /// Copies every chunk into an owned [`Png`], failing on the first bad CRC
+    pub fn to_png(&self) -> Result<Png> {
+        let chunks = self
+            .chunks()
+            .map(|chunk| Ok(chunk?.check_crc()?.to_chunk()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Png::from_chunks(chunks))
+    }
+}
+
+/// Iterator over the chunks of a [`PngRef`]
+#[derive(Debug, Clone)]
+pub struct ChunkRefs<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> ChunkRefs<'a> {
+    /// Byte offset of the next chunk from the start of the file
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn read_chunk(&mut self) -> Result<Option<ChunkRef<'a>>> {
+        let rest = &self.bytes[self.offset..];
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        let truncated = PngmeError::TruncatedChunk {
+            offset: self.offset,
+        };
+        if rest.len() < Chunk::METADATA_BYTES {
+            return Err(truncated);
+        }
+
+        let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let chunk_type = <[u8; 4]>::try_from(&rest[4..8]).unwrap();
+        ChunkType::try_from(chunk_type)?;
+        if rest.len() - Chunk::METADATA_BYTES < length {
+            return Err(truncated);
+        }
+        let data = &rest[8..8 + length];
+        let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+
+        self.offset += Chunk::METADATA_BYTES + length;
+        Ok(Some(ChunkRef {
+            chunk_type,
+            data,
+            crc,
+        }))
+    }
+}
+
+impl<'a> Iterator for ChunkRefs<'a> {
+    type Item = Result<ChunkRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.read_chunk().transpose();
+        if !matches!(next, Some(Ok(_))) {
+            self.done = true;
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hidden".to_vec()),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+        .as_bytes()
+    }
+
+    #[test]
+    pub fn test_borrowed_chunks() {
+        let bytes = testing_bytes();
+        let png = PngRef::new(&bytes).unwrap();
+        let chunks = png.chunks().collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[1].chunk_type().to_string(), "ruSt");
+        assert_eq!(chunks[1].data(), b"hidden");
+        assert!(std::ptr::eq(chunks[1].data(), &bytes[41..47]));
+        assert!(chunks.iter().all(ChunkRef::is_crc_valid));
+        assert_eq!(png.to_png().unwrap().as_bytes(), bytes);
+    }
+
+    #[test]
+    pub fn test_bad_crc() {
+        let mut bytes = testing_bytes();
+        bytes[47] ^= 1;
+        let png = PngRef::new(&bytes).unwrap();
+        let chunk = png.chunks().nth(1).unwrap().unwrap();
+
+        assert!(!chunk.is_crc_valid());
+        assert!(!chunk.to_chunk().is_crc_valid());
+        assert!(matches!(
+            chunk.check_crc(),
+            Err(PngmeError::InvalidCrc { .. })
+        ));
+        assert!(matches!(png.to_png(), Err(PngmeError::InvalidCrc { .. })));
+    }
+
+    #[test]
+    pub fn test_truncated() {
+        let bytes = testing_bytes();
+        let png = PngRef::new(&bytes[..bytes.len() - 1]).unwrap();
+        let mut chunks = png.chunks();
+
+        assert!(chunks.next().unwrap().is_ok());
+        assert!(chunks.next().unwrap().is_ok());
+        assert!(matches!(
+            chunks.next(),
+            Some(Err(PngmeError::TruncatedChunk { offset: 51 }))
+        ));
+        assert!(chunks.next().is_none());
+        assert!(matches!(
+            PngRef::new(&bytes[..4]),
+            Err(PngmeError::MissingSignature)
+        ));
+    }
+}
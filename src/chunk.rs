@@ -0,0 +1,345 @@
+use std::fmt::Display;
+
+use crc::{Crc, CRC_32_ISO_HDLC};
+
+use crate::borrowed::ChunkRef;
+use crate::chunk_type::ChunkType;
+use crate::compression;
+use crate::error::PngmeError;
+use crate::Result;
+
+/// CRC-32 as used by PNG (ISO 3309 / ITU-T V.42), computed over the chunk type and chunk data.
+const PNG_CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// A PNG chunk. Each chunk consists of four parts:
+/// - length: a 4-byte unsigned integer giving the number of bytes in the chunk's data field
+/// - chunk type: a 4-byte chunk type code
+/// - chunk data: the data bytes appropriate to the chunk type, if any
+/// - CRC: a 4-byte CRC calculated on the preceding bytes in the chunk, excluding the length field
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    length: u32,
+    chunk_type: ChunkType,
+    data: Vec<u8>,
+    crc: u32,
+}
+
+impl Chunk {
+    /// Number of bytes taken up by the length, chunk type and CRC fields
+    pub const METADATA_BYTES: usize = 12;
+
+    /// Largest data length the PNG spec allows in a single chunk
+    pub const MAX_LENGTH: usize = (1 << 31) - 1;
+
+    /// Creates a new chunk from a chunk type and its data, computing the length and CRC
+    pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
+        let crc = Self::compute_crc(&chunk_type, &data);
+        Chunk {
+            length: data.len() as u32,
+            chunk_type,
+            data,
+            crc,
+        }
+    }
+
+    /// Creates a new chunk holding the zlib-compressed data, marked so it can be decompressed
+    /// transparently with [`Chunk::decompressed_data`]
+    pub fn new_compressed(chunk_type: ChunkType, data: &[u8]) -> Chunk {
+        Chunk::new(chunk_type, compression::compress(data))
+    }
+
+    /// Number of bytes in the chunk's data field
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// The chunk type code of this chunk
+    pub fn chunk_type(&self) -> &ChunkType {
+        &self.chunk_type
+    }
+
+    /// The data bytes of this chunk
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The CRC of this chunk, as stored in the file it was read from
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    /// Checks whether the stored CRC matches the CRC calculated over the chunk type and data.
+    /// Always true unless the chunk was read without CRC checking.
+    pub fn is_crc_valid(&self) -> bool {
+        self.crc == Self::compute_crc(&self.chunk_type, &self.data)
+    }
+
+    /// The data bytes of this chunk, decompressed if they were stored compressed
+    pub fn decompressed_data(&self) -> Result<Vec<u8>> {
+        compression::decompress(&self.data)
+    }
+
+    /// Interprets the chunk data as a UTF-8 string
+    pub fn data_as_string(&self) -> Result<String> {
+        Ok(String::from_utf8(self.data.clone())?)
+    }
+
+    /// Serializes the chunk as length, chunk type, data and CRC
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.length
+            .to_be_bytes()
+            .iter()
+            .chain(self.chunk_type.bytes().iter())
+            .chain(self.data.iter())
+            .chain(self.crc.to_be_bytes().iter())
+            .copied()
+            .collect()
+    }
+
+    /// Creates a chunk from fields read out of a file, checking the stored CRC against the data
+    pub(crate) fn from_parts(chunk_type: ChunkType, data: Vec<u8>, crc: u32) -> Result<Chunk> {
+        let chunk = Chunk::new(chunk_type, data);
+        if chunk.crc != crc {
+            return Err(PngmeError::InvalidCrc {
+                chunk_type: chunk.chunk_type,
+                expected: chunk.crc,
+                actual: crc,
+            });
+        }
+        Ok(chunk)
+    }
+
+    /// Creates a chunk from fields read out of a file, keeping the stored CRC even if it is wrong
+    pub(crate) fn from_parts_unchecked(chunk_type: ChunkType, data: Vec<u8>, crc: u32) -> Chunk {
+        Chunk {
+            length: data.len() as u32,
+            chunk_type,
+            data,
+            crc,
+        }
+    }
+
+    pub(crate) fn compute_crc(chunk_type: &ChunkType, data: &[u8]) -> u32 {
+        let mut digest = PNG_CRC.digest();
+        digest.update(&chunk_type.bytes());
+        digest.update(data);
+        digest.finalize()
+    }
+}
+
+impl TryFrom<&[u8]> for Chunk {
+    type Error = PngmeError;
+
+    fn try_from(value: &[u8]) -> Result<Self> {
+        if value.len() < Chunk::METADATA_BYTES {
+            return Err(PngmeError::TruncatedChunk { offset: 0 });
+        }
+
+        let (length_bytes, rest) = value.split_at(4);
+        let length = u32::from_be_bytes(length_bytes.try_into().unwrap());
+        if (length as usize).checked_add(8) != Some(rest.len()) {
+            return Err(PngmeError::InvalidChunkLength {
+                declared: length,
+                available: rest.len() - 8,
+            });
+        }
+
+        let (type_bytes, rest) = rest.split_at(4);
+        let chunk_type = ChunkType::try_from(<[u8; 4]>::try_from(type_bytes).unwrap())?;
+
+        let (data, crc_bytes) = rest.split_at(length as usize);
+        let crc = u32::from_be_bytes(crc_bytes.try_into().unwrap());
+
+        Chunk::from_parts(chunk_type, data.to_vec(), crc)
+    }
+}
+
+impl Display for Chunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        ChunkRef::from(self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn testing_chunk() -> Chunk {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+        let message_bytes = "This is where your secret message will be!".as_bytes();
+        let crc: u32 = 2882656334;
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .chain(crc.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        Chunk::try_from(chunk_data.as_ref()).unwrap()
+    }
+
+    #[test]
+    pub fn test_new_chunk() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = "This is where your secret message will be!"
+            .as_bytes()
+            .to_vec();
+        let chunk = Chunk::new(chunk_type, data);
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    pub fn test_chunk_length() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.length(), 42);
+    }
+
+    #[test]
+    pub fn test_chunk_type() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.chunk_type().to_string(), String::from("RuSt"));
+    }
+
+    #[test]
+    pub fn test_chunk_string() {
+        let chunk = testing_chunk();
+        let chunk_string = chunk.data_as_string().unwrap();
+        let expected_chunk_string = String::from("This is where your secret message will be!");
+        assert_eq!(chunk_string, expected_chunk_string);
+    }
+
+    #[test]
+    pub fn test_chunk_crc() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    pub fn test_valid_chunk_from_bytes() {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+        let message_bytes = "This is where your secret message will be!".as_bytes();
+        let crc: u32 = 2882656334;
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .chain(crc.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        let chunk = Chunk::try_from(chunk_data.as_ref()).unwrap();
+
+        let chunk_string = chunk.data_as_string().unwrap();
+        let expected_chunk_string = String::from("This is where your secret message will be!");
+
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.chunk_type().to_string(), String::from("RuSt"));
+        assert_eq!(chunk_string, expected_chunk_string);
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    pub fn test_invalid_chunk_from_bytes() {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+        let message_bytes = "This is where your secret message will be!".as_bytes();
+        let crc: u32 = 2882656333;
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .chain(crc.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        let chunk = Chunk::try_from(chunk_data.as_ref());
+
+        assert!(matches!(
+            chunk,
+            Err(PngmeError::InvalidCrc {
+                expected: 2882656334,
+                actual: 2882656333,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    pub fn test_unchecked_chunk_keeps_stored_crc() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::from_parts_unchecked(chunk_type.clone(), b"data".to_vec(), 1234);
+
+        assert_eq!(chunk.crc(), 1234);
+        assert!(!chunk.is_crc_valid());
+        assert!(Chunk::new(chunk_type, b"data".to_vec()).is_crc_valid());
+    }
+
+    #[test]
+    pub fn test_truncated_chunk_from_bytes() {
+        let chunk_data = testing_chunk().as_bytes();
+
+        assert!(matches!(
+            Chunk::try_from(&chunk_data[..8]),
+            Err(PngmeError::TruncatedChunk { .. })
+        ));
+        assert!(matches!(
+            Chunk::try_from(&chunk_data[..chunk_data.len() - 1]),
+            Err(PngmeError::InvalidChunkLength {
+                declared: 42,
+                available: 41
+            })
+        ));
+
+        let mut huge = u32::MAX.to_be_bytes().to_vec();
+        huge.extend_from_slice(b"RuSt\0\0\0\0");
+        assert!(matches!(
+            Chunk::try_from(huge.as_slice()),
+            Err(PngmeError::InvalidChunkLength {
+                declared: u32::MAX,
+                available: 0
+            })
+        ));
+    }
+
+    #[test]
+    pub fn test_chunk_as_bytes_round_trip() {
+        let chunk = testing_chunk();
+        let bytes = chunk.as_bytes();
+        let parsed = Chunk::try_from(bytes.as_ref()).unwrap();
+
+        assert_eq!(bytes.len(), Chunk::METADATA_BYTES + 42);
+        assert_eq!(parsed.as_bytes(), bytes);
+    }
+
+    #[test]
+    pub fn test_new_compressed_chunk() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let message = "This is where your secret message will be!".repeat(10);
+        let chunk = Chunk::new_compressed(chunk_type, message.as_bytes());
+
+        assert!((chunk.length() as usize) < message.len());
+        assert_eq!(chunk.decompressed_data().unwrap(), message.as_bytes());
+        assert_eq!(
+            testing_chunk().decompressed_data().unwrap(),
+            testing_chunk().data()
+        );
+    }
+
+    #[test]
+    pub fn test_chunk_trait_impls() {
+        let chunk = testing_chunk();
+        let _chunk_string = format!("{}", chunk);
+    }
+}
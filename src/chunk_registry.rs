@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::chunk_type::ChunkType;
+use crate::manifest::MANIFEST_CHUNK_TYPE;
+use crate::signature::SIGNATURE_CHUNK_TYPE;
+use crate::Result;
+
+/// The chunk types defined by the PNG specification and its registered extensions, with the
+/// APNG chunks and a few widely used private ones: type code, name and description
+const STANDARD_CHUNKS: &[(&str, &str, &str)] = &[
+    (
+        "IHDR",
+        "Image header",
+        "width, height, bit depth and color type",
+    ),
+    ("PLTE", "Palette", "colors for indexed images"),
+    ("IDAT", "Image data", "compressed, filtered pixel rows"),
+    ("IEND", "Image trailer", "marks the end of the file"),
+    (
+        "tRNS",
+        "Transparency",
+        "transparent colors or palette alpha",
+    ),
+    ("cHRM", "Chromaticities", "white point and primaries"),
+    ("gAMA", "Gamma", "image gamma"),
+    ("iCCP", "ICC profile", "embedded color profile"),
+    ("sBIT", "Significant bits", "original sample depths"),
+    ("sRGB", "Standard RGB", "image uses the sRGB color space"),
+    (
+        "cICP",
+        "Coding-independent code points",
+        "color space for HDR images",
+    ),
+    (
+        "mDCV",
+        "Mastering display color volume",
+        "HDR mastering display",
+    ),
+    ("cLLI", "Content light level", "HDR maximum light levels"),
+    ("tEXt", "Text", "uncompressed Latin-1 text"),
+    ("zTXt", "Compressed text", "zlib-compressed Latin-1 text"),
+    (
+        "iTXt",
+        "International text",
+        "UTF-8 text, optionally compressed",
+    ),
+    ("bKGD", "Background color", "default background"),
+    (
+        "hIST",
+        "Palette histogram",
+        "how often each palette color is used",
+    ),
+    ("pHYs", "Physical dimensions", "pixel size or aspect ratio"),
+    (
+        "sPLT",
+        "Suggested palette",
+        "palette for displays with fewer colors",
+    ),
+    ("eXIf", "Exif", "Exif metadata"),
+    (
+        "tIME",
+        "Modification time",
+        "when the image was last changed",
+    ),
+    ("acTL", "Animation control", "APNG frame and loop count"),
+    (
+        "fcTL",
+        "Frame control",
+        "APNG frame size, position and timing",
+    ),
+    (
+        "fdAT",
+        "Frame data",
+        "APNG pixel data for a frame after the first",
+    ),
+    ("oFFs", "Image offset", "position on a printed page"),
+    (
+        "pCAL",
+        "Pixel calibration",
+        "maps samples to physical values",
+    ),
+    (
+        "sCAL",
+        "Physical scale",
+        "physical size of the image subject",
+    ),
+    ("sTER", "Stereo", "image holds a stereo pair"),
+    (
+        "gIFg",
+        "GIF graphic control",
+        "delay and disposal from a converted GIF",
+    ),
+    (
+        "gIFx",
+        "GIF application extension",
+        "application data from a converted GIF",
+    ),
+    ("dSIG", "Digital signature", "signature over the file"),
+    (
+        "iDOT",
+        "Apple decoding hints",
+        "split points for parallel decoding",
+    ),
+    (
+        "CgBI",
+        "Apple iOS optimization",
+        "nonstandard byte order and compression",
+    ),
+    (
+        SIGNATURE_CHUNK_TYPE,
+        "pngme signature",
+        "Ed25519 signature over a payload",
+    ),
+    (
+        MANIFEST_CHUNK_TYPE,
+        "pngme manifest",
+        "names of the payloads stored in the image",
+    ),
+];
+
+/// What a chunk type is for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkDescription {
+    /// Short name of the chunk type, e.g. "Image header"
+    pub name: String,
+    /// What the chunk holds
+    pub description: String,
+}
+
+/// A lookup table from chunk type codes to their descriptions.
+///
+/// [`ChunkRegistry::default`] knows the standard chunk types. Programs can add their own codes
+/// with [`ChunkRegistry::register`].
+#[derive(Debug, Clone)]
+pub struct ChunkRegistry {
+    chunks: HashMap<ChunkType, ChunkDescription>,
+}
+
+impl ChunkRegistry {
+    /// A registry that knows no chunk types at all
+    pub fn empty() -> ChunkRegistry {
+        ChunkRegistry {
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Describes a chunk type, replacing and returning any earlier description of it
+    pub fn register(
+        &mut self,
+        chunk_type: &str,
+        name: &str,
+        description: &str,
+    ) -> Result<Option<ChunkDescription>> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let description = ChunkDescription {
+            name: name.to_string(),
+            description: description.to_string(),
+        };
+        Ok(self.chunks.insert(chunk_type, description))
+    }
+
+    /// The description of a chunk type, if it is known
+    pub fn describe(&self, chunk_type: &ChunkType) -> Option<&ChunkDescription> {
+        self.chunks.get(chunk_type)
+    }
+}
+
+impl Default for ChunkRegistry {
+    fn default() -> ChunkRegistry {
+        let mut registry = ChunkRegistry::empty();
+        for (chunk_type, name, description) in STANDARD_CHUNKS {
+            registry
+                .register(chunk_type, name, description)
+                .expect("standard chunk types are valid");
+        }
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_standard_chunks() {
+        let registry = ChunkRegistry::default();
+        let ihdr = registry
+            .describe(&ChunkType::from_str("IHDR").unwrap())
+            .unwrap();
+
+        assert_eq!(ihdr.name, "Image header");
+        assert!(registry
+            .describe(&ChunkType::from_str("acTL").unwrap())
+            .is_some());
+        assert!(registry
+            .describe(&ChunkType::from_str("ruSt").unwrap())
+            .is_none());
+        assert!(ChunkRegistry::empty()
+            .describe(&ChunkType::from_str("IHDR").unwrap())
+            .is_none());
+    }
+
+    #[test]
+    pub fn test_register() {
+        let mut registry = ChunkRegistry::default();
+        let rust_type = ChunkType::from_str("ruSt").unwrap();
+
+        assert_eq!(
+            registry
+                .register("ruSt", "Build stamp", "CI build metadata")
+                .unwrap(),
+            None
+        );
+        assert_eq!(registry.describe(&rust_type).unwrap().name, "Build stamp");
+        let previous = registry.register("ruSt", "Stamp", "metadata").unwrap();
+        assert_eq!(previous.unwrap().name, "Build stamp");
+        assert!(registry
+            .register("ru5t", "Bad", "not a chunk type")
+            .is_err());
+    }
+}
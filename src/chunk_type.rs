@@ -1,11 +1,13 @@
 use std::{fmt::Display, str::FromStr};
 
+use crate::error::PngmeError;
+
 /// A 4-byte chunk type code. Type codes are restricted to consist of uppercase and lowercase ASCII letters
 /// (A-Z and a-z, or 65-90 and 97-122 decimal). However, encoders and decoders must treat the codes as fixed
 /// binary values, not character strings. For example, it would not be correct to represent the type code
 /// IDAT by the EBCDIC equivalents of those letters.
-#[derive(Debug, PartialEq, Eq)]
-struct ChunkType {
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChunkType {
     ancillary: u8,
     private: u8,
     reserved: u8,
@@ -13,69 +15,90 @@ struct ChunkType {
 }
 
 impl ChunkType {
-    /// Creates a Chunk Type from an array of bytes
+    /// Bit 5 of each byte, which distinguishes uppercase from lowercase ASCII letters
+    const PROPERTY_BIT: u8 = 1 << 5;
+
+    /// Creates a chunk type from its 4-byte code, failing if a byte is not an ASCII letter
+    pub fn new(code: [u8; 4]) -> Result<ChunkType, PngmeError> {
+        ChunkType::try_from(code)
+    }
+
+    /// Starts building a chunk type from four letters, whose case the builder then adjusts to
+    /// give the type the properties asked for:
+    ///
+    /// ```
+    /// use pngme::ChunkType;
+    ///
+    /// let chunk_type = ChunkType::custom("RUST")
+    ///     .ancillary(true)
+    ///     .private(true)
+    ///     .safe_to_copy(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(chunk_type.to_string(), "ruSt");
+    /// ```
+    pub fn custom(letters: &str) -> ChunkTypeBuilder {
+        ChunkTypeBuilder {
+            letters: letters.to_string(),
+            ancillary: None,
+            private: None,
+            safe_to_copy: None,
+        }
+    }
+
+    /// Turns any four letters into a chunk type suited to hiding data: ancillary so decoders may
+    /// ignore it, private so it cannot clash with a registered type, and safe to copy so editors
+    /// keep it. "rust", "RUST" and "ruSt" all give "ruSt".
+    pub fn suggest_type(letters: &str) -> Result<ChunkType, PngmeError> {
+        ChunkType::custom(letters)
+            .ancillary(true)
+            .private(true)
+            .safe_to_copy(true)
+            .build()
+    }
+
+    /// Returns the raw bytes of the Chunk Type
     pub fn bytes(&self) -> [u8; 4] {
         [self.ancillary, self.private, self.reserved, self.safe_to_copy]
     }
 
     /// Checks whether the Chunk Type is a valid chunk type
     /// A valid chunk type has:
-    /// - ancillary byte as ascii alphabetic
-    /// - private byte as ascii alphabetic
-    /// - reserved byte as ascii Uppercase
-    /// - safe to copy byte as ascii alphabetic
+    /// - every byte as ascii alphabetic
+    /// - the reserved bit set to 0
     pub fn is_valid(&self) -> bool {
-        self.ancillary.is_ascii_alphabetic() &&
-        self.private.is_ascii_alphabetic() &&
-        self.reserved.is_ascii_uppercase() &&
-        self.safe_to_copy.is_ascii_alphabetic()
+        self.bytes().iter().all(u8::is_ascii_alphabetic) && self.is_reserved_bit_valid()
     }
 
     /// Determine if the ancillary byte is critical
     /// Ancillary bit: bit 5 of first byte
     /// 0 (uppercase) = critical, 1 (lowercase) = ancillary. 
     pub fn is_critical(&self) -> bool {
-        if self.ancillary & 32 == 0 {
-            true
-        } else {
-            false
-        }
+        self.ancillary & Self::PROPERTY_BIT == 0
     }
 
     /// Determine if the private byte is public
     /// Private bit: bit 5 of second byte
     /// 0 (uppercase) = public, 1 (lowercase) = private.
     pub fn is_public(&self) -> bool {
-        if self.private & 32 == 0 {
-            true
-        } else {
-            false
-        }
+        self.private & Self::PROPERTY_BIT == 0
     }
 
     /// Determine if the reserved byte is valid
     /// Reserved bit: bit 5 of third byte
     /// Must be 0 (uppercase) in files conforming to this version of PNG. 
     pub fn is_reserved_bit_valid(&self) -> bool {
-        if self.reserved.is_ascii_uppercase() {
-            true
-        } else {
-            false
-        }
+        self.reserved & Self::PROPERTY_BIT == 0
     }
 
     /// Determine if safe to copy byte is unsafe to copy
     /// Safe-to-copy bit: bit 5 of fourth byte
     /// 0 (uppercase) = unsafe to copy, 1 (lowercase) = safe to copy. 
     pub fn is_unsafe_to_copy(&self) -> bool {
-        if self.safe_to_copy & 32 == 0 {
-            true
-        } else {
-            false
-        }
+        self.safe_to_copy & Self::PROPERTY_BIT == 0
     }
 
-    /// Determine if safe to copy byte is unsafe to copy
+    /// Determine if safe to copy byte is safe to copy
     /// Safe-to-copy bit: bit 5 of fourth byte
     /// 0 (uppercase) = unsafe to copy, 1 (lowercase) = safe to copy. 
     pub fn is_safe_to_copy(&self) -> bool {
@@ -83,15 +106,61 @@ impl ChunkType {
     }
 }
 
+/// Builds a [`ChunkType`] by setting its property bits, see [`ChunkType::custom`].
+///
+/// Properties that are not set keep the case of the letters given, except for the reserved bit,
+/// which is always cleared so the type is valid.
+#[derive(Debug, Clone)]
+pub struct ChunkTypeBuilder {
+    letters: String,
+    ancillary: Option<bool>,
+    private: Option<bool>,
+    safe_to_copy: Option<bool>,
+}
+
+impl ChunkTypeBuilder {
+    /// Whether decoders may ignore the chunk (lowercase first letter) or must understand it
+    pub fn ancillary(mut self, ancillary: bool) -> Self {
+        self.ancillary = Some(ancillary);
+        self
+    }
+
+    /// Whether the type is private to an application (lowercase second letter) or public
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = Some(private);
+        self
+    }
+
+    /// Whether editors may copy the chunk into a modified image (lowercase fourth letter)
+    pub fn safe_to_copy(mut self, safe_to_copy: bool) -> Self {
+        self.safe_to_copy = Some(safe_to_copy);
+        self
+    }
+
+    /// Builds the chunk type, failing unless the letters are exactly four ASCII letters
+    pub fn build(self) -> Result<ChunkType, PngmeError> {
+        let mut bytes = ChunkType::from_str(&self.letters)?.bytes();
+        let properties = [self.ancillary, self.private, Some(false), self.safe_to_copy];
+        for (byte, lowercase) in bytes.iter_mut().zip(properties) {
+            match lowercase {
+                Some(true) => *byte |= ChunkType::PROPERTY_BIT,
+                Some(false) => *byte &= !ChunkType::PROPERTY_BIT,
+                None => {}
+            }
+        }
+        ChunkType::new(bytes)
+    }
+}
+
 impl TryFrom<[u8; 4]> for ChunkType {
-    type Error = &'static str;
+    type Error = PngmeError;
 
     fn try_from(value: [u8; 4]) -> Result<Self, Self::Error> {
-        for i in 0..4 {
-            let byte = value[i];
-            if !byte.is_ascii_uppercase() && !byte.is_ascii_lowercase() {
-                return Err("Invalid Type Code");
-            }
+        if let Some(position) = value.iter().position(|byte| !byte.is_ascii_alphabetic()) {
+            return Err(PngmeError::InvalidChunkType {
+                byte: value[position],
+                position,
+            });
         }
         Ok(ChunkType {
             ancillary: value[0],
@@ -103,22 +172,14 @@ impl TryFrom<[u8; 4]> for ChunkType {
 }
 
 impl FromStr for ChunkType {
-    type Err = &'static str;
+    type Err = PngmeError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        let str_bytes = value.as_bytes();
-        for i in 0..4 {
-            let byte = str_bytes[i];
-            if !byte.is_ascii_uppercase() && !byte.is_ascii_lowercase() {
-                return Err("Invalid Type Code");
-            }
-        }
-        Ok(ChunkType {
-            ancillary: str_bytes[0],
-            private: str_bytes[1],
-            reserved: str_bytes[2],
-            safe_to_copy: str_bytes[3],
-        })
+        let bytes: [u8; 4] = value
+            .as_bytes()
+            .try_into()
+            .map_err(|_| PngmeError::InvalidChunkTypeLength(value.len()))?;
+        ChunkType::try_from(bytes)
     }
 }
 
@@ -217,7 +278,30 @@ mod tests {
         assert!(!chunk.is_valid());
 
         let chunk = ChunkType::from_str("Ru1t");
-        assert!(chunk.is_err());
+        assert!(matches!(
+            chunk,
+            Err(PngmeError::InvalidChunkType { byte: b'1', position: 2 })
+        ));
+
+        assert!(matches!(
+            ChunkType::from_str("Ru"),
+            Err(PngmeError::InvalidChunkTypeLength(2))
+        ));
+        assert!(ChunkType::from_str("RuStt").is_err());
+    }
+
+    #[test]
+    pub fn test_chunk_type_standard_properties() {
+        let chunk = ChunkType::from_str("IHDR").unwrap();
+        assert!(chunk.is_critical());
+        assert!(chunk.is_public());
+        assert!(chunk.is_reserved_bit_valid());
+        assert!(chunk.is_unsafe_to_copy());
+
+        let chunk = ChunkType::from_str("tEXt").unwrap();
+        assert!(!chunk.is_critical());
+        assert!(chunk.is_public());
+        assert!(chunk.is_safe_to_copy());
     }
 
     #[test]
@@ -226,6 +310,25 @@ mod tests {
         assert_eq!(&chunk.to_string(), "RuSt");
     }
 
+    #[test]
+    pub fn test_chunk_type_builder() {
+        let chunk_type = ChunkType::custom("rust").ancillary(false).build().unwrap();
+        assert_eq!(&chunk_type.to_string(), "RuSt");
+        assert!(chunk_type.is_valid());
+
+        let chunk_type = ChunkType::custom("RUST")
+            .private(true)
+            .safe_to_copy(true)
+            .build()
+            .unwrap();
+        assert_eq!(&chunk_type.to_string(), "RuSt");
+
+        let suggested = ChunkType::suggest_type("RUST").unwrap();
+        assert_eq!(ChunkType::new(*b"ruSt").unwrap(), suggested);
+        assert!(ChunkType::new(*b"ru5t").is_err());
+        assert!(ChunkType::custom("rusty").build().is_err());
+    }
+
     #[test]
     pub fn test_chunk_type_trait_impls() {
         let chunk_type_1: ChunkType = TryFrom::try_from([82, 117, 83, 116]).unwrap();
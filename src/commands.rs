@@ -0,0 +1,1625 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use pngme::apng::{self, Animation, ANIMATION_CHUNK_TYPES};
+use pngme::chunk_registry::ChunkRegistry;
+use pngme::diff::{self, ChunkState};
+use pngme::envelope::{self, Envelope};
+use pngme::validate::{self, ValidationIssue};
+use pngme::{
+    compression, crypto, mac, segment, signature, Chunk, ChunkRef, ChunkType, Png, PngReader,
+    PngmeError, Result, TextEntry,
+};
+
+use clap::CommandFactory;
+use serde_json::json;
+
+use crate::args::{
+    CapacityArgs, Cli, CompletionsArgs, DecodeArgs, DiffArgs, EmbedMode, EncodeArgs, ExtractArgs,
+    InfoArgs, InsertArgs, InspectArgs, LsPayloadsArgs, ManpageArgs, OutputFormat, PasswordArgs,
+    PrintArgs, RemoveArgs, RepairArgs, ScanArgs, StripArgs, TextEncoding, TextGetArgs, TextSetArgs,
+    VerifyArgs,
+};
+use crate::batch;
+use crate::encoding;
+use crate::output;
+use crate::secrets;
+
+/// Encodes a message or file into a PNG file and saves the result
+pub fn encode(args: EncodeArgs) -> Result<()> {
+    let chunk_type = resolve_chunk_type(&args.chunk_type, args.auto_type)?;
+    let password = match secrets::password(&args.password, true)? {
+        Some(password) => Some(password),
+        None => secrets::default_password(&args.password)?,
+    };
+    let hmac_key = secrets::hmac_key(&args.hmac_key, true)?;
+    if args.auto_type {
+        let output = args.output.as_deref().unwrap_or(&args.file_path);
+        report(output, &format!("Using chunk type {}", chunk_type));
+    }
+    let payload = match &args.input_file {
+        Some(path) if is_stdio(path) && is_stdio(&args.file_path) => {
+            return Err(PngmeError::InvalidArgument(
+                "the PNG and the payload cannot both be read from stdin".to_string(),
+            ))
+        }
+        Some(path) => read_input(path)?,
+        None => encoding::from_text(args.message.as_deref().unwrap_or_default(), args.encoding)?,
+    };
+    let payload = match args.raw {
+        true => {
+            let payload = match args.compress {
+                true => compression::compress(&payload),
+                false => payload,
+            };
+            match &password {
+                Some(password) => crypto::encrypt(&payload, password)?,
+                None => payload,
+            }
+        }
+        false => {
+            let filename = args
+                .input_file
+                .as_deref()
+                .filter(|path| !is_stdio(path))
+                .and_then(Path::file_name)
+                .and_then(|name| name.to_str());
+            Envelope::seal(&payload, filename, args.compress, password.as_deref())?.to_bytes()
+        }
+    };
+    let payload = match &hmac_key {
+        Some(key) => mac::append_hmac(&chunk_type, &payload, key)?,
+        None => payload,
+    };
+    if args.name.is_some() {
+        check_named_mode(args.mode)?;
+    }
+    let sign_key = match &args.sign_key {
+        Some(path) => Some(signature::read_signing_key(&read_key(path)?)?),
+        None => None,
+    };
+
+    let encode_file = |input: &Path, output: &Path| -> Result<()> {
+        let mut png = read_png(input)?;
+        let animated = has_animation(&png);
+        let before = args.dry_run.then(|| png.clone());
+        match args.mode {
+            EmbedMode::Chunk => match &args.name {
+                Some(name) => png.encode_named(
+                    name,
+                    &chunk_type,
+                    &payload,
+                    args.max_chunk_size,
+                    args.position.position(),
+                )?,
+                None => png.encode_segmented_at(
+                    &chunk_type,
+                    &payload,
+                    args.max_chunk_size,
+                    args.position.position(),
+                )?,
+            },
+            EmbedMode::Lsb => png.encode_lsb(&chunk_type, &payload)?,
+        }
+        if let Some(key) = &sign_key {
+            png.sign_payload(&chunk_type, &payload, key)?;
+        }
+        check_animation(output, animated, &png)?;
+        if let Some(before) = before {
+            print_dry_run(output, &before, &png);
+            return Ok(());
+        }
+        back_up(output, args.backup)?;
+        write_png(output, &png)
+    };
+    if args.batch.recursive {
+        let files = batch_files(&args.file_path)?;
+        return batch::run(&files, args.batch.jobs, |file| encode_file(file, file));
+    }
+    let output = args.output.as_deref().unwrap_or(&args.file_path);
+    encode_file(&args.file_path, output)
+}
+
+/// Searches for a message hidden in a PNG file and prints it if one is found
+pub fn decode(args: DecodeArgs, format: OutputFormat) -> Result<()> {
+    let password = secrets::password(&args.password, false)?;
+    let hmac_key = secrets::hmac_key(&args.hmac_key, false)?;
+    let (chunk_type, message) = match (&args.name, &args.chunk_type) {
+        (Some(name), _) => {
+            check_named_mode(args.mode)?;
+            let png = read_png(&args.file_path)?;
+            let manifest = png.manifest()?;
+            let entry = manifest
+                .get(name)
+                .ok_or_else(|| PngmeError::PayloadNotFound(name.clone()))?;
+            let chunk_type = entry.chunk_type.to_string();
+            let message = segment::join(png.payload_chunks(entry)?)?;
+            verify_signature(&png, &chunk_type, &message, args.verify_key.as_deref())?;
+            (chunk_type, message)
+        }
+        (None, Some(chunk_type)) => {
+            let chunk_type = &resolve_chunk_type(chunk_type, args.auto_type)?;
+            let message = match (args.mode, &args.verify_key) {
+                (EmbedMode::Chunk, None) => read_segments(&args.file_path, chunk_type)?,
+                (mode, verify_key) => {
+                    let png = read_png(&args.file_path)?;
+                    let message = match mode {
+                        EmbedMode::Chunk => png.decode_segmented(chunk_type)?,
+                        EmbedMode::Lsb => png.decode_lsb(chunk_type)?,
+                    };
+                    verify_signature(&png, chunk_type, &message, verify_key.as_deref())?;
+                    message
+                }
+            };
+            (chunk_type.clone(), message)
+        }
+        (None, None) => unreachable!("clap requires a chunk type unless --name is given"),
+    };
+    let message = match &hmac_key {
+        Some(key) => mac::strip_hmac(&chunk_type, &message, key)?.to_vec(),
+        None => message,
+    };
+    let (message, filename) =
+        open_message(&message, args.raw, password, &args.password).map_err(|err| {
+            let tagged = message.len() >= mac::HMAC_BYTES
+                && Envelope::from_bytes(&message[..message.len() - mac::HMAC_BYTES]).is_ok();
+            match (err, tagged) {
+                (PngmeError::InvalidEnvelope(reason), true) => PngmeError::InvalidEnvelope(
+                    format!("{}, pass --hmac-key to check the HMAC after it", reason),
+                ),
+                (err, _) => err,
+            }
+        })?;
+    if let Some(path) = &args.output_file {
+        write_output(path, &message)?;
+    }
+    match (format, &args.output_file) {
+        (OutputFormat::Text, Some(_)) => {}
+        (OutputFormat::Text, None) => say!("{}", encoding::to_text(&message, args.encoding)?),
+        (OutputFormat::Json, output_file) => {
+            let mut report = json!({
+                "chunk_type": chunk_type,
+                "name": args.name,
+                "length": message.len(),
+                "filename": filename,
+            });
+            match (output_file, args.encoding, std::str::from_utf8(&message)) {
+                (Some(path), _, _) => report["output_file"] = json!(path),
+                (None, TextEncoding::Utf8, Ok(text)) => report["message"] = json!(text),
+                (None, TextEncoding::Utf8 | TextEncoding::Hex, _) => {
+                    report["hex"] = json!(hex(&message))
+                }
+                (None, TextEncoding::Base64, _) => {
+                    report["base64"] = json!(encoding::to_text(&message, TextEncoding::Base64)?)
+                }
+            }
+            print_json(&report);
+        }
+    }
+    Ok(())
+}
+
+/// Reads back a decoded payload with the passphrase given on the command line or, failing that,
+/// the key file from the config file. The key file only applies to payloads that may be
+/// encrypted, and since a bare payload does not say whether it is, one the key does not decrypt
+/// is read as stored.
+fn open_message(
+    message: &[u8],
+    raw: bool,
+    password: Option<String>,
+    args: &PasswordArgs,
+) -> Result<(Vec<u8>, Option<String>)> {
+    let open = |password: Option<&str>| match raw {
+        true => Ok((envelope::open_bare(message, password)?, None)),
+        false => envelope::open_payload(message, password),
+    };
+    if password.is_some() {
+        return open(password.as_deref());
+    }
+    let bare = raw || !envelope::is_envelope(message);
+    let encrypted = bare || Envelope::from_bytes(message).is_ok_and(|envelope| envelope.encrypted);
+    let key = match encrypted {
+        true => secrets::default_password(args)?,
+        false => None,
+    };
+    match open(key.as_deref()) {
+        Err(PngmeError::DecryptionFailed) if bare && key.is_some() => open(None),
+        opened => opened,
+    }
+}
+
+/// With --auto-type, turns the letters given into a chunk type fit for hiding data
+fn resolve_chunk_type(chunk_type: &str, auto_type: bool) -> Result<String> {
+    match auto_type {
+        true => ChunkType::suggest_type(chunk_type)
+            .map(|chunk_type| chunk_type.to_string())
+            .map_err(|err| PngmeError::InvalidArgument(err.to_string())),
+        false => Ok(chunk_type_arg(chunk_type)?.to_string()),
+    }
+}
+
+/// Parses a chunk type given on the command line. A malformed one is a usage error, whereas a
+/// malformed chunk type read from a file means the PNG is damaged
+fn chunk_type_arg(chunk_type: &str) -> Result<ChunkType> {
+    ChunkType::from_str(chunk_type).map_err(|err| PngmeError::InvalidArgument(err.to_string()))
+}
+
+/// Checks a payload against the signature stored for it, if a key to check with was given
+fn verify_signature(
+    png: &Png,
+    chunk_type: &str,
+    message: &[u8],
+    verify_key: Option<&Path>,
+) -> Result<()> {
+    match verify_key {
+        Some(path) => {
+            let key = signature::read_verifying_key(&read_key(path)?)?;
+            png.verify_payload(chunk_type, message, &key)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Named payloads are found through the manifest, which only records chunks
+fn check_named_mode(mode: EmbedMode) -> Result<()> {
+    match mode {
+        EmbedMode::Chunk => Ok(()),
+        EmbedMode::Lsb => Err(PngmeError::InvalidArgument(
+            "--name cannot be used with --mode lsb".to_string(),
+        )),
+    }
+}
+
+/// Lists the payloads stored by name, with the chunk type and size of each
+pub fn ls_payloads(args: LsPayloadsArgs, format: OutputFormat) -> Result<()> {
+    let png = read_png(&args.file_path)?;
+    let manifest = png.manifest()?;
+    let payloads: Vec<_> = manifest
+        .entries()
+        .iter()
+        .map(|entry| {
+            let bytes = png
+                .payload_chunks(entry)
+                .ok()
+                .map(|chunks| chunks.iter().map(|data| data.len()).sum::<usize>());
+            (entry, bytes)
+        })
+        .collect();
+
+    if format == OutputFormat::Json {
+        let payloads: Vec<_> = payloads
+            .iter()
+            .map(|(entry, bytes)| {
+                json!({
+                    "name": entry.name,
+                    "chunk_type": entry.chunk_type.to_string(),
+                    "chunks": entry.crcs.len(),
+                    "bytes": bytes,
+                    "missing": bytes.is_none(),
+                })
+            })
+            .collect();
+        print_json(&json!({ "payloads": payloads }));
+        return Ok(());
+    }
+
+    if payloads.is_empty() {
+        say!("No named payloads");
+        return Ok(());
+    }
+    let width = payloads
+        .iter()
+        .map(|(entry, _)| entry.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("NAME".len());
+    say!("{:<width$}  TYPE  CHUNKS       BYTES", "NAME");
+    for (entry, bytes) in &payloads {
+        let bytes = match bytes {
+            Some(bytes) => bytes.to_string(),
+            None => "missing".to_string(),
+        };
+        say!(
+            "{:<width$}  {}  {:>6}  {:>10}",
+            entry.name,
+            entry.chunk_type,
+            entry.crcs.len(),
+            bytes
+        );
+    }
+    Ok(())
+}
+
+/// Reads the chunks stored under a chunk type and joins them back into one payload, stopping as
+/// soon as the payload is complete
+fn read_segments(path: &Path, chunk_type: &str) -> Result<Vec<u8>> {
+    if LENIENT.load(Ordering::Relaxed) {
+        return read_png(path)?.decode_segmented(chunk_type);
+    }
+    let parsed_type = ChunkType::from_str(chunk_type)?;
+    let mut parts = Vec::new();
+    for chunk in open_png(path)? {
+        let chunk = chunk?;
+        if *chunk.chunk_type() == parsed_type {
+            let is_segment = segment::is_segment(chunk.data());
+            parts.push(chunk);
+            if !is_segment {
+                break;
+            }
+        }
+    }
+    if parts.is_empty() {
+        return Err(PngmeError::ChunkNotFound(chunk_type.to_string()));
+    }
+    segment::join(parts.iter().map(Chunk::data))
+}
+
+/// Removes a chunk from a PNG file and saves the result
+pub fn remove(args: RemoveArgs) -> Result<()> {
+    chunk_type_arg(&args.chunk_type)?;
+    let remove_from = |file: &Path| -> Result<Vec<Chunk>> {
+        let mut png = read_png(file)?;
+        let animated = has_animation(&png);
+        let before = args.dry_run.then(|| png.clone());
+        let removed = match (args.all, args.index) {
+            (true, _) => png.remove_all_chunks(&args.chunk_type)?,
+            (false, Some(nth)) => vec![remove_nth_chunk(&mut png, &args.chunk_type, nth)?],
+            (false, None) => vec![png.remove_chunk(&args.chunk_type)?],
+        };
+        check_animation(file, animated, &png)?;
+        if let Some(before) = before {
+            print_dry_run(file, &before, &png);
+            return Ok(removed);
+        }
+        back_up(file, args.backup)?;
+        write_png(file, &png)?;
+        Ok(removed)
+    };
+    if args.batch.recursive {
+        let files = batch_files(&args.file_path)?;
+        return batch::run(&files, args.batch.jobs, |file| {
+            remove_from(file).map(|_| ())
+        });
+    }
+    let removed = remove_from(&args.file_path)?;
+    if args.dry_run {
+        return Ok(());
+    }
+    report(
+        &args.file_path,
+        &format!(
+            "Removed {} chunk(s) of type {}",
+            removed.len(),
+            args.chunk_type
+        ),
+    );
+    Ok(())
+}
+
+/// Removes the nth chunk of a type, counting from 0
+fn remove_nth_chunk(png: &mut Png, chunk_type: &str, nth: usize) -> Result<Chunk> {
+    let parsed_type = ChunkType::from_str(chunk_type)?;
+    let index = png
+        .chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| *chunk.chunk_type() == parsed_type)
+        .nth(nth)
+        .map(|(index, _)| index)
+        .ok_or_else(|| PngmeError::ChunkNotFound(format!("{} #{}", chunk_type, nth)))?;
+    png.remove_chunk_at(index)
+}
+
+/// Wraps the bytes of a file in a chunk with the right length and CRC and inserts it
+pub fn insert(args: InsertArgs) -> Result<()> {
+    if is_stdio(&args.data_file) && is_stdio(&args.file_path) {
+        return Err(PngmeError::InvalidArgument(
+            "the PNG and the chunk data cannot both be read from stdin".to_string(),
+        ));
+    }
+    let data = read_input(&args.data_file)?;
+    if data.len() > Chunk::MAX_LENGTH {
+        return Err(PngmeError::PayloadTooLarge {
+            size: data.len(),
+            capacity: Chunk::MAX_LENGTH,
+        });
+    }
+    let chunk = Chunk::new(chunk_type_arg(&args.chunk_type)?, data);
+    let length = chunk.length();
+
+    let mut png = read_png(&args.file_path)?;
+    let animated = has_animation(&png);
+    let before = args.dry_run.then(|| png.clone());
+    png.insert_chunk(chunk, args.position.position())?;
+    let output = args.output.as_deref().unwrap_or(&args.file_path);
+    check_animation(output, animated, &png)?;
+    if let Some(before) = before {
+        print_dry_run(output, &before, &png);
+        return Ok(());
+    }
+    back_up(output, args.backup)?;
+    write_png(output, &png)?;
+    report(
+        output,
+        &format!(
+            "Inserted a {} chunk with {} data bytes",
+            args.chunk_type, length
+        ),
+    );
+    Ok(())
+}
+
+/// Writes the raw data of a chunk to a file, without decoding it
+pub fn extract(args: ExtractArgs) -> Result<()> {
+    chunk_type_arg(&args.chunk_type)?;
+    let png = read_png(&args.file_path)?;
+    let data = png.chunk_data(&args.chunk_type, args.index)?;
+    write_output(&args.output, data)?;
+    report(
+        &args.output,
+        &format!(
+            "Wrote {} bytes from {} chunk #{} to {}",
+            data.len(),
+            args.chunk_type,
+            args.index,
+            args.output.display()
+        ),
+    );
+    Ok(())
+}
+
+/// Prints the header fields of one chunk and a hexdump of its data, with file offsets
+pub fn inspect(args: InspectArgs, format: OutputFormat) -> Result<()> {
+    let chunk_type = chunk_type_arg(&args.chunk_type)?;
+    let mut nth = 0;
+    let mut found = None;
+    for_each_chunk(&args.file_path, |position, offset, chunk| {
+        if chunk.chunk_type() != chunk_type {
+            return Ok(ControlFlow::Continue(()));
+        }
+        if nth < args.index {
+            nth += 1;
+            return Ok(ControlFlow::Continue(()));
+        }
+        found = Some((position, offset, chunk.to_chunk()));
+        Ok(ControlFlow::Break(()))
+    })?;
+    let (position, offset, chunk) = found
+        .ok_or_else(|| PngmeError::ChunkNotFound(format!("{} #{}", args.chunk_type, args.index)))?;
+
+    let data = chunk.data();
+    let data_offset = offset + 8;
+    let shown = &data[..args.bytes.map_or(data.len(), |limit| limit.min(data.len()))];
+    let computed = Chunk::new(chunk_type.clone(), data.to_vec()).crc();
+    let registry = ChunkRegistry::default();
+    let known = registry.describe(&chunk_type);
+
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "type": chunk_type.to_string(),
+            "index": args.index,
+            "position": position,
+            "offset": offset,
+            "data_offset": data_offset,
+            "length": chunk.length(),
+            "crc": chunk.crc(),
+            "computed_crc": computed,
+            "crc_valid": chunk.is_crc_valid(),
+            "critical": chunk_type.is_critical(),
+            "public": chunk_type.is_public(),
+            "safe_to_copy": chunk_type.is_safe_to_copy(),
+            "name": known.map(|known| &known.name),
+            "hex": hex(shown),
+            "truncated": shown.len() < data.len(),
+        }));
+        return Ok(());
+    }
+
+    say!(
+        "Chunk:      {} #{} (chunk {} in the file)",
+        chunk_type,
+        args.index,
+        position
+    );
+    if let Some(known) = known {
+        say!("Name:       {}: {}", known.name, known.description);
+    }
+    say!("Offset:     {} ({:#x})", offset, offset);
+    say!(
+        "Length:     {} bytes, data at {} ({:#x})",
+        chunk.length(),
+        data_offset,
+        data_offset
+    );
+    match chunk.is_crc_valid() {
+        true => say!("CRC:        {:08x} (ok)", chunk.crc()),
+        false => say!(
+            "CRC:        {:08x} (BAD, computed {:08x})",
+            chunk.crc(),
+            computed
+        ),
+    }
+    say!(
+        "Properties: {}, {}, {}",
+        flag(chunk_type.is_critical(), "critical", "ancillary"),
+        flag(chunk_type.is_public(), "public", "private"),
+        flag(
+            chunk_type.is_safe_to_copy(),
+            "safe to copy",
+            "unsafe to copy"
+        )
+    );
+    if !shown.is_empty() {
+        say!();
+        for line in hexdump_at(shown, data_offset) {
+            say!("{}", line);
+        }
+    }
+    if shown.len() < data.len() {
+        say!("... {} more byte(s)", data.len() - shown.len());
+    }
+    Ok(())
+}
+
+/// Removes every ancillary chunk from a PNG file, except for the types asked to be kept
+pub fn strip(args: StripArgs) -> Result<()> {
+    let keep = args
+        .keep
+        .iter()
+        .map(|chunk_type| chunk_type_arg(chunk_type))
+        .collect::<Result<Vec<_>>>()?;
+    let strip_file = |file: &Path, output: &Path| -> Result<Vec<Chunk>> {
+        let mut png = read_png(file)?;
+        let animated = has_animation(&png);
+        let before = args.dry_run.then(|| png.clone());
+        let removed = png.retain_chunks(|chunk| {
+            let chunk_type = chunk.chunk_type();
+            chunk_type.is_critical()
+                || keep.contains(chunk_type)
+                || (animated && ANIMATION_CHUNK_TYPES.contains(&chunk_type.to_string().as_str()))
+        });
+        check_animation(output, animated, &png)?;
+        if let Some(before) = before {
+            print_dry_run(output, &before, &png);
+            return Ok(removed);
+        }
+        back_up(output, args.backup)?;
+        write_png(output, &png)?;
+        Ok(removed)
+    };
+    if args.batch.recursive {
+        let files = batch_files(&args.file_path)?;
+        return batch::run(&files, args.batch.jobs, |file| {
+            strip_file(file, file).map(|_| ())
+        });
+    }
+    let output = args.output.as_deref().unwrap_or(&args.file_path);
+    let removed = strip_file(&args.file_path, output)?;
+    if args.dry_run {
+        return Ok(());
+    }
+    report(
+        output,
+        &format!("Removed {} ancillary chunk(s)", removed.len()),
+    );
+    Ok(())
+}
+
+/// Prints all of the chunks in a PNG file
+pub fn print_chunks(args: PrintArgs, format: OutputFormat) -> Result<()> {
+    let mut chunks = Vec::new();
+    for_each_chunk(&args.file_path, |_, _, chunk| {
+        let chunk = chunk.check_crc()?;
+        match format {
+            OutputFormat::Text if !output::is_quiet() => output::print(&chunk.to_string()),
+            OutputFormat::Text => {}
+            OutputFormat::Json => chunks.push(json!({
+                "type": chunk.chunk_type().to_string(),
+                "length": chunk.length(),
+                "crc": chunk.crc(),
+            })),
+        }
+        Ok(ControlFlow::Continue(()))
+    })?;
+    if format == OutputFormat::Json {
+        print_json(&json!({ "chunks": chunks }));
+    }
+    Ok(())
+}
+
+/// Prints the image metadata stored in the IHDR chunk of a PNG file
+pub fn info(args: InfoArgs, format: OutputFormat) -> Result<()> {
+    let header = read_png(&args.file_path)?.header()?;
+    match format {
+        OutputFormat::Text => {
+            say!("Dimensions: {}x{}", header.width, header.height);
+            say!("Bit depth: {}", header.bit_depth);
+            say!("Color type: {}", header.color_type);
+            say!("Interlaced: {}", flag(header.interlaced, "yes", "no"));
+        }
+        OutputFormat::Json => print_json(&json!({
+            "width": header.width,
+            "height": header.height,
+            "bit_depth": header.bit_depth,
+            "color_type": header.color_type.to_string(),
+            "interlaced": header.interlaced,
+        })),
+    }
+    Ok(())
+}
+
+/// Prints how many payload bytes a PNG file can hold in each embedding mode, and whether a given
+/// payload fits
+pub fn capacity(args: CapacityArgs, format: OutputFormat) -> Result<()> {
+    let png = read_png(&args.file_path)?;
+    let overhead = envelope::ENVELOPE_HEADER_BYTES
+        + match args.encrypted {
+            true => crypto::OVERHEAD_BYTES,
+            false => 0,
+        };
+    let lsb = match png.lsb_capacity() {
+        Ok(capacity) => Ok(capacity.saturating_sub(overhead)),
+        Err(PngmeError::UnsupportedImage(reason)) => Err(reason),
+        Err(err) => return Err(err),
+    };
+    let chunk = Chunk::MAX_LENGTH - overhead;
+    let payload = match &args.input_file {
+        Some(path) => {
+            let payload = read_input(path)?;
+            Some((payload.len(), compression::compress(&payload).len()))
+        }
+        None => None,
+    };
+    let fits = |size: usize| lsb.as_ref().is_ok_and(|capacity| size <= *capacity);
+
+    match format {
+        OutputFormat::Text => {
+            say!(
+                "chunk mode: {} bytes per chunk, unlimited when split across chunks",
+                chunk
+            );
+            match &lsb {
+                Ok(capacity) => say!("lsb mode: {} bytes", *capacity),
+                Err(reason) => say!("lsb mode: unavailable, {}", reason),
+            }
+            if let Some((size, compressed)) = payload {
+                say!("payload: {} bytes, {} compressed", size, compressed);
+                say!(
+                    "fits in lsb mode: {} as is, {} compressed",
+                    flag(fits(size), "yes", "no"),
+                    flag(fits(compressed), "yes", "no")
+                );
+            }
+        }
+        OutputFormat::Json => {
+            let mut report = json!({
+                "chunk": { "max_chunk_bytes": chunk, "segmented_bytes": null },
+                "lsb": match &lsb {
+                    Ok(capacity) => json!({ "bytes": capacity }),
+                    Err(reason) => json!({ "bytes": null, "unsupported": reason }),
+                },
+            });
+            if let Some((size, compressed)) = payload {
+                report["payload"] = json!({
+                    "bytes": size,
+                    "compressed_bytes": compressed,
+                    "fits_lsb": fits(size),
+                    "fits_lsb_compressed": fits(compressed),
+                });
+            }
+            print_json(&report);
+        }
+    }
+    Ok(())
+}
+
+/// Prints a table of every chunk in a PNG file with its offset, CRC status and property flags
+pub fn scan(args: ScanArgs, format: OutputFormat) -> Result<()> {
+    let registry = args.describe.then(ChunkRegistry::default);
+    let heading = "INDEX  TYPE      LENGTH      OFFSET  CRC  CRITICAL   PUBLIC   SAFE-TO-COPY";
+    let heading = match registry {
+        Some(_) => format!("{}  DESCRIPTION", heading),
+        None => heading.to_string(),
+    };
+    let mut reports = Vec::new();
+    let mut seen = Vec::new();
+    for_each_chunk(&args.file_path, |index, offset, chunk| {
+        seen.push(animation_outline(chunk));
+        let chunk_type = chunk.chunk_type();
+        if format == OutputFormat::Json {
+            let mut report = json!({
+                "index": index,
+                "type": chunk_type.to_string(),
+                "length": chunk.length(),
+                "offset": offset,
+                "crc": chunk.crc(),
+                "crc_valid": chunk.is_crc_valid(),
+                "critical": chunk_type.is_critical(),
+                "public": chunk_type.is_public(),
+                "safe_to_copy": chunk_type.is_safe_to_copy(),
+            });
+            if let Some(registry) = &registry {
+                let known = registry.describe(&chunk_type);
+                report["known"] = json!(known.is_some());
+                report["name"] = json!(known.map(|known| &known.name));
+                report["description"] = json!(known.map(|known| &known.description));
+            }
+            reports.push(report);
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        if index == 0 {
+            say!("{}", heading);
+        }
+        let safe_to_copy = flag(chunk_type.is_safe_to_copy(), "safe", "unsafe");
+        let last_columns = match registry
+            .as_ref()
+            .map(|registry| registry.describe(&chunk_type))
+        {
+            None => safe_to_copy.to_string(),
+            Some(Some(known)) => {
+                format!("{:12}  {}: {}", safe_to_copy, known.name, known.description)
+            }
+            Some(None) if chunk_type.is_public() => {
+                format!("{:12}  unknown public chunk", safe_to_copy)
+            }
+            Some(None) => format!("{:12}  UNKNOWN PRIVATE CHUNK", safe_to_copy),
+        };
+        say!(
+            "{:>5}  {:4}  {:>10}  {:>10}  {:3}  {:9}  {:7}  {}",
+            index,
+            chunk_type.to_string(),
+            chunk.length(),
+            offset,
+            flag(chunk.is_crc_valid(), "ok", "BAD"),
+            flag(chunk_type.is_critical(), "critical", "ancillary"),
+            flag(chunk_type.is_public(), "public", "private"),
+            last_columns,
+        );
+        Ok(ControlFlow::Continue(()))
+    })?;
+
+    let seen: Vec<ChunkRef> = seen.iter().map(ChunkRef::from).collect();
+    if format == OutputFormat::Json {
+        let animation = apng::read_animation(&seen);
+        print_json(&json!({
+            "chunks": reports,
+            "animation": animation.as_ref().ok().and_then(Option::as_ref).map(animation_json),
+            "animation_error": animation.err().map(|err| err.to_string()),
+        }));
+        return Ok(());
+    }
+    if seen.is_empty() {
+        say!("{}", heading);
+    }
+    match apng::read_animation(&seen) {
+        Ok(None) => {}
+        Ok(Some(animation)) => {
+            let plays = match animation.num_plays {
+                0 => "forever".to_string(),
+                plays => format!("{} time(s)", plays),
+            };
+            say!();
+            say!(
+                "Animation: {} frame(s), played {}",
+                animation.num_frames,
+                plays
+            );
+            for (number, frame) in animation.frames.iter().enumerate() {
+                say!(
+                    "  frame {}: fcTL #{}, {}x{} at {},{}, delay {}/{} s, dispose {}, blend {}, {} {} chunk(s)",
+                    number,
+                    frame.index,
+                    frame.width,
+                    frame.height,
+                    frame.x_offset,
+                    frame.y_offset,
+                    frame.delay_num,
+                    frame_delay_den(frame.delay_den),
+                    frame.dispose_op,
+                    frame.blend_op,
+                    frame.data_chunks,
+                    flag(frame.uses_idat, "IDAT", "fdAT"),
+                );
+            }
+        }
+        Err(err) => {
+            say!();
+            say!("Animation: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// A frame delay denominator of 0 means hundredths of a second
+fn frame_delay_den(delay_den: u16) -> u16 {
+    match delay_den {
+        0 => 100,
+        delay_den => delay_den,
+    }
+}
+
+/// Copies what the animation summary of a scan needs from a chunk: all of an acTL or fcTL chunk,
+/// and only the sequence number of an fdAT chunk and the type of any other, so a streamed scan
+/// does not hold on to the image data
+fn animation_outline(chunk: ChunkRef) -> Chunk {
+    let kept = match chunk.chunk_type().to_string().as_str() {
+        "acTL" | "fcTL" => chunk.data(),
+        "fdAT" => &chunk.data()[..chunk.data().len().min(4)],
+        _ => &[],
+    };
+    Chunk::new(chunk.chunk_type(), kept.to_vec())
+}
+
+fn animation_json(animation: &Animation) -> serde_json::Value {
+    let frames: Vec<_> = animation
+        .frames
+        .iter()
+        .map(|frame| {
+            json!({
+                "sequence_number": frame.sequence_number,
+                "index": frame.index,
+                "width": frame.width,
+                "height": frame.height,
+                "x_offset": frame.x_offset,
+                "y_offset": frame.y_offset,
+                "delay_num": frame.delay_num,
+                "delay_den": frame_delay_den(frame.delay_den),
+                "dispose_op": frame.dispose_op,
+                "blend_op": frame.blend_op,
+                "uses_idat": frame.uses_idat,
+                "data_chunks": frame.data_chunks,
+            })
+        })
+        .collect();
+    json!({
+        "num_frames": animation.num_frames,
+        "num_plays": animation.num_plays,
+        "frames": frames,
+    })
+}
+
+/// Prints the chunks added, removed or modified between two PNG files
+pub fn diff(args: DiffArgs, format: OutputFormat) -> Result<()> {
+    let before = read_png(&args.before)?;
+    let after = read_png(&args.after)?;
+    let changes = diff::diff(&before, &after);
+
+    if format == OutputFormat::Json {
+        let state_report = |state: Option<&ChunkState>| {
+            state.map(|state| {
+                json!({
+                    "index": state.index,
+                    "offset": state.offset,
+                    "length": state.length,
+                    "crc": state.crc,
+                })
+            })
+        };
+        let reports: Vec<_> = changes
+            .iter()
+            .map(|change| {
+                let mut report = json!({
+                    "change": change.kind(),
+                    "type": change.chunk_type().to_string(),
+                    "before": state_report(change.before()),
+                    "after": state_report(change.after()),
+                });
+                if args.hexdump {
+                    report["before_data"] = json!(changed_data(&before, change.before()).map(hex));
+                    report["after_data"] = json!(changed_data(&after, change.after()).map(hex));
+                }
+                report
+            })
+            .collect();
+        print_json(&json!({
+            "before": args.before,
+            "after": args.after,
+            "changes": reports,
+        }));
+        return Ok(());
+    }
+
+    if changes.is_empty() {
+        say!("no chunks differ");
+    }
+    for change in &changes {
+        say!("{}", change);
+        if args.hexdump {
+            let old = hexdump(changed_data(&before, change.before()).unwrap_or_default());
+            let new = hexdump(changed_data(&after, change.after()).unwrap_or_default());
+            for row in 0..old.len().max(new.len()) {
+                let (old, new) = (old.get(row), new.get(row));
+                if old == new {
+                    continue;
+                }
+                if let Some(old) = old {
+                    say!("  - {}", old);
+                }
+                if let Some(new) = new {
+                    say!("  + {}", new);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The data of the chunk a diff refers to, unless that side of the diff has no chunk
+fn changed_data<'a>(png: &'a Png, state: Option<&ChunkState>) -> Option<&'a [u8]> {
+    state.map(|state| png.chunks()[state.index].data())
+}
+
+/// Formats bytes as hexdump rows of 16 bytes, each with its offset and the printable characters
+fn hexdump(bytes: &[u8]) -> Vec<String> {
+    hexdump_at(bytes, 0)
+}
+
+/// Like [`hexdump`], with offsets counted from `start` instead of 0
+fn hexdump_at(bytes: &[u8], start: usize) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(row, bytes)| {
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let text: String = bytes
+                .iter()
+                .map(|&byte| match byte.is_ascii_graphic() || byte == b' ' {
+                    true => byte as char,
+                    false => '.',
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<47}  |{}|",
+                start + row * 16,
+                hex.join(" "),
+                text
+            )
+        })
+        .collect()
+}
+
+/// Checks a PNG file for problems and prints each one found
+pub fn verify(args: VerifyArgs, format: OutputFormat) -> Result<()> {
+    let issues = with_input(&args.file_path, |bytes| Ok(validate::validate_bytes(bytes)))?;
+    match format {
+        OutputFormat::Text if issues.is_empty() => {
+            say!("{}: ok", args.file_path.display())
+        }
+        OutputFormat::Text => {
+            for issue in &issues {
+                say!("{}: {}", args.file_path.display(), issue);
+            }
+        }
+        OutputFormat::Json => {
+            let issue_reports: Vec<_> = issues
+                .iter()
+                .map(|issue| json!({ "kind": issue.kind(), "message": issue.to_string() }))
+                .collect();
+            print_json(&json!({
+                "file": args.file_path,
+                "valid": issues.is_empty(),
+                "issues": issue_reports,
+            }));
+        }
+    }
+    if issues.is_empty() {
+        return Ok(());
+    }
+    // A file whose only problems are bad CRCs fails as a CRC mismatch, which has its own exit code
+    if let Some(ValidationIssue::BadCrc {
+        chunk_type,
+        stored,
+        computed,
+        ..
+    }) = issues.first()
+    {
+        if issues
+            .iter()
+            .all(|issue| matches!(issue, ValidationIssue::BadCrc { .. }))
+        {
+            return Err(PngmeError::InvalidCrc {
+                chunk_type: chunk_type.clone(),
+                expected: *computed,
+                actual: *stored,
+            });
+        }
+    }
+    Err(PngmeError::ValidationFailed {
+        issues: issues.len(),
+    })
+}
+
+/// Fixes bad CRCs in a PNG file, and drops unreadable trailing data if asked to
+pub fn repair(args: RepairArgs) -> Result<()> {
+    let bytes = read_input(&args.file_path)?;
+    let (png, issues) = validate::read_damaged(&bytes);
+    let mut png = png.ok_or(PngmeError::MissingSignature)?;
+
+    let mut dropped = 0;
+    if let Some(ValidationIssue::UnreadableData { offset, reason }) = issues.first() {
+        if !args.drop_trailing {
+            say_status!("Pass --drop-trailing to remove data that cannot be read as chunks");
+            return Err(PngmeError::UnreadableData {
+                offset: *offset,
+                reason: reason.clone(),
+            });
+        }
+        dropped = bytes.len() - offset;
+    }
+    let before = args.dry_run.then(|| png.clone());
+    let repaired = png.repair_crcs();
+
+    let output = args.output.as_deref().unwrap_or(&args.file_path);
+    if let Some(before) = before {
+        print_dry_run(output, &before, &png);
+        say!(
+            "Would repair {} CRC(s) and drop {} trailing byte(s)",
+            repaired,
+            dropped
+        );
+        return Ok(());
+    }
+    write_png(output, &png)?;
+    report(
+        output,
+        &format!(
+            "Repaired {} CRC(s) and dropped {} trailing byte(s)",
+            repaired, dropped
+        ),
+    );
+    Ok(())
+}
+
+/// Prints a completion script for a shell, or writes it into a directory
+pub fn completions(args: CompletionsArgs) -> Result<()> {
+    let mut command = Cli::command();
+    let Some(dir) = &args.dir else {
+        let mut script = Vec::new();
+        clap_complete::generate(args.shell, &mut command, "pngme", &mut script);
+        return write_output(Path::new("-"), &script);
+    };
+    fs::create_dir_all(dir).map_err(|err| with_path(dir, err))?;
+    let path = clap_complete::generate_to(args.shell, &mut command, "pngme", dir)
+        .map_err(|err| with_path(dir, err))?;
+    say!("Wrote {}", path.display());
+    Ok(())
+}
+
+/// Prints the man page, or writes the pages of pngme and all its subcommands into a directory
+pub fn manpage(args: ManpageArgs) -> Result<()> {
+    let command = Cli::command();
+    let Some(dir) = &args.dir else {
+        let mut page = Vec::new();
+        clap_mangen::Man::new(command).render(&mut page)?;
+        return write_output(Path::new("-"), &page);
+    };
+    fs::create_dir_all(dir).map_err(|err| with_path(dir, err))?;
+    clap_mangen::generate_to(command, dir).map_err(|err| with_path(dir, err))?;
+    say!("Wrote man pages to {}", dir.display());
+    Ok(())
+}
+
+/// Prints the text entries of a PNG file
+pub fn text_get(args: TextGetArgs) -> Result<()> {
+    let entries = read_png(&args.file_path)?.text_entries()?;
+    match &args.keyword {
+        Some(keyword) => {
+            let entry = entries
+                .iter()
+                .find(|entry| entry.keyword() == keyword)
+                .ok_or_else(|| PngmeError::KeywordNotFound(keyword.clone()))?;
+            say!("{}", entry.text());
+        }
+        None => {
+            for entry in &entries {
+                say!("{}: {}", entry.keyword(), entry.text());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Stores a text entry in a PNG file and saves the result
+pub fn text_set(args: TextSetArgs) -> Result<()> {
+    let mut png = read_png(&args.file_path)?;
+    png.set_text(&TextEntry::new(&args.keyword, &args.value, args.compress)?)?;
+    write_png(&args.file_path, &png)
+}
+
+/// Whether a PNG is an APNG whose animation is intact
+fn has_animation(png: &Png) -> bool {
+    matches!(png.animation(), Ok(Some(_)))
+}
+
+/// Refuses to write a PNG whose animation was intact before an edit and is broken after it
+fn check_animation(output: &Path, animated: bool, png: &Png) -> Result<()> {
+    match png.animation() {
+        Err(err) if animated => {
+            say_status!(
+                "Refusing to write {}: the change would break its animation",
+                output.display()
+            );
+            Err(err)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Path that stands for stdin when reading and stdout when writing
+const STDIO: &str = "-";
+
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO
+}
+
+/// Opens a file for reading, or stdin for `-`
+fn open_input(path: &Path) -> Result<Box<dyn Read>> {
+    if is_stdio(path) {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file = File::open(path).map_err(|err| with_path(path, err))?;
+    Ok(Box::new(BufReader::new(file)))
+}
+
+fn read_input(path: &Path) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open_input(path)?
+        .read_to_end(&mut bytes)
+        .map_err(|err| with_path(path, err))?;
+    tracing::debug!(path = %path.display(), bytes = bytes.len(), "read input");
+    Ok(bytes)
+}
+
+/// Runs `f` over the bytes of a file, or of stdin for `-`. With the `mmap` feature files are
+/// memory-mapped instead of being read into memory. Commands that can look at one chunk at a time
+/// use [`for_each_chunk`] instead.
+fn with_input<T>(path: &Path, f: impl FnOnce(&[u8]) -> Result<T>) -> Result<T> {
+    #[cfg(feature = "mmap")]
+    if !is_stdio(path) {
+        let mapped = pngme::MappedFile::open(path).map_err(|err| match err {
+            PngmeError::Io(err) => with_path(path, err),
+            err => err,
+        })?;
+        return f(mapped.as_bytes());
+    }
+    f(&read_input(path)?)
+}
+
+/// Calls `f` with the position, offset and contents of each chunk of a file, or of stdin for `-`,
+/// whatever its CRC, until `f` breaks. With the `mmap` feature files are memory-mapped and the
+/// chunks borrowed from the mapping; otherwise they are read one at a time, so the whole file is
+/// never in memory.
+fn for_each_chunk(
+    path: &Path,
+    mut f: impl FnMut(usize, usize, ChunkRef) -> Result<ControlFlow<()>>,
+) -> Result<()> {
+    #[cfg(feature = "mmap")]
+    if !is_stdio(path) {
+        return with_input(path, |bytes| {
+            let mut chunks = pngme::PngRef::new(bytes)?.chunks();
+            for position in 0.. {
+                let offset = chunks.offset();
+                let Some(chunk) = chunks.next() else { break };
+                if f(position, offset, chunk?)?.is_break() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+    }
+    let mut chunks = open_png(path)?.without_crc_check();
+    for position in 0.. {
+        let offset = chunks.offset();
+        let Some(chunk) = chunks.next() else { break };
+        if f(position, offset, ChunkRef::from(&chunk?))?.is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn read_key(path: &Path) -> Result<String> {
+    Ok(String::from_utf8(read_input(path)?)?)
+}
+
+/// Writes bytes to a file, or to stdout for `-`
+fn write_output(path: &Path, bytes: &[u8]) -> Result<()> {
+    if is_stdio(path) {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(bytes)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+    if path.file_name().is_none() {
+        return Err(PngmeError::InvalidArgument(format!(
+            "{}: output path has no file name",
+            path.display()
+        )));
+    }
+    write_atomically(path, bytes).map_err(|err| with_path(path, err))?;
+    tracing::debug!(path = %path.display(), bytes = bytes.len(), "wrote output");
+    Ok(())
+}
+
+/// Counts temporary files so that parallel batch jobs never pick the same name
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Writes a file by writing a temporary file next to it and renaming it into place, so that a
+/// crash part way through leaves either the old file or the new one, never a mix
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .expect("write_output checks for a file name");
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        file_name.to_string_lossy(),
+        process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    write_through(path, &temp_path, bytes)
+}
+
+/// Writes a file through the given temporary file, removing the temporary file if anything fails
+fn write_through(path: &Path, temp_path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let written = (|| {
+        let mut file = File::create(temp_path)?;
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(temp_path, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(temp_path);
+    }
+    written
+}
+
+/// Copies a file that is about to be overwritten to `<file>.bak`
+fn back_up(path: &Path, backup: bool) -> Result<()> {
+    if !backup || is_stdio(path) || !path.exists() {
+        return Ok(());
+    }
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(".bak");
+    fs::copy(path, &backup_path).map_err(|err| with_path(Path::new(&backup_path), err))?;
+    Ok(())
+}
+
+/// Prints what writing a PNG would change, in place of writing it. The report is printed in one
+/// go so that reports from parallel batch jobs do not interleave.
+fn print_dry_run(output: &Path, before: &Png, after: &Png) {
+    let mut lines = vec![format!(
+        "{}: would write {} bytes, {} before",
+        output.display(),
+        after.as_bytes().len(),
+        before.as_bytes().len()
+    )];
+    let changes = diff::diff(before, after);
+    if changes.is_empty() {
+        lines.push("  no chunks would change".to_string());
+    }
+    lines.extend(changes.iter().map(|change| format!("  {}", change)));
+    say!("{}", lines.join("\n"));
+}
+
+/// Prints a status message, keeping it out of stdout when a PNG is being written there
+fn report(output: &Path, message: &str) {
+    match is_stdio(output) {
+        true => say_status!("{}", message),
+        false => say!("{}", message),
+    }
+}
+
+fn print_json(value: &serde_json::Value) {
+    say!("{}", value);
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn flag(set: bool, yes: &'static str, no: &'static str) -> &'static str {
+    if set {
+        yes
+    } else {
+        no
+    }
+}
+
+/// Expands the target of a --recursive operation, which always works on files in place
+fn batch_files(target: &Path) -> Result<Vec<PathBuf>> {
+    if is_stdio(target) {
+        return Err(PngmeError::InvalidArgument(
+            "--recursive needs a directory or glob pattern, not stdin".to_string(),
+        ));
+    }
+    batch::expand(target)
+}
+
+/// Whether `--lenient` was passed, see [`read_png`]
+static LENIENT: AtomicBool = AtomicBool::new(false);
+
+/// Makes damaged chunks a warning instead of an error for the rest of the run
+pub fn set_lenient(lenient: bool) {
+    LENIENT.store(lenient, Ordering::Relaxed);
+}
+
+/// Reads a whole PNG file. With `--lenient`, chunks that cannot be read are skipped with a
+/// warning instead of failing.
+fn read_png(path: &Path) -> Result<Png> {
+    let bytes = read_input(path)?;
+    if !LENIENT.load(Ordering::Relaxed) {
+        return Png::try_from(bytes.as_ref());
+    }
+    let (png, issues) = Png::from_bytes_lossy(&bytes);
+    for issue in &issues {
+        say_status!("warning: {}: {}", path.display(), issue);
+    }
+    Ok(png)
+}
+
+fn open_png(path: &Path) -> Result<PngReader<Box<dyn Read>>> {
+    PngReader::new(open_input(path)?)
+}
+
+fn write_png(path: &Path, png: &Png) -> Result<()> {
+    write_output(path, &png.as_bytes())
+}
+
+/// Prefixes an I/O error with the path it happened on, so the user knows which file to look at
+fn with_path(path: &Path, err: io::Error) -> PngmeError {
+    PngmeError::Io(io::Error::new(
+        err.kind(),
+        format!("{}: {}", path.display(), err),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::PngMeArgs;
+    use crate::config::Config;
+    use crate::exit_code;
+    use clap::Parser;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pngme-commands-{}-{}", process::id(), name))
+    }
+
+    fn testing_png(path: &Path) {
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ]);
+        fs::write(path, png.as_bytes()).unwrap();
+    }
+
+    fn parse(args: &[&str]) -> PngMeArgs {
+        Cli::parse_from([&["pngme"], args].concat()).command
+    }
+
+    #[test]
+    pub fn test_payload_round_trip_with_library() {
+        let path = temp_path("payload.png");
+        let output = temp_path("payload.txt");
+        let file = path.to_str().unwrap();
+        testing_png(&path);
+
+        match parse(&[
+            "encode",
+            file,
+            "ruSt",
+            "from the cli",
+            "--password",
+            "secret",
+        ]) {
+            PngMeArgs::Encode(args) => encode(args).unwrap(),
+            _ => unreachable!(),
+        }
+        let png = Png::from_file(&path).unwrap();
+        assert_eq!(
+            png.decode_payload("ruSt", Some("secret")).unwrap(),
+            b"from the cli"
+        );
+
+        let mut png = Png::from_file(&path).unwrap();
+        png.encode_payload("laTe", b"from the library", true, None)
+            .unwrap();
+        fs::write(&path, png.as_bytes()).unwrap();
+        let args = [
+            "decode",
+            file,
+            "laTe",
+            "--output-file",
+            output.to_str().unwrap(),
+        ];
+        match parse(&args) {
+            PngMeArgs::Decode(args) => decode(args, OutputFormat::Text).unwrap(),
+            _ => unreachable!(),
+        }
+        assert_eq!(fs::read(&output).unwrap(), b"from the library");
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&output);
+        let _ = fs::remove_file(path.with_extension("png.bak"));
+    }
+
+    #[test]
+    pub fn test_typed_chunk_type_is_a_usage_error() {
+        let path = temp_path("usage.png");
+        let file = path.to_str().unwrap();
+        testing_png(&path);
+
+        let err = match parse(&["encode", file, "12", "hello"]) {
+            PngMeArgs::Encode(args) => encode(args).unwrap_err(),
+            _ => unreachable!(),
+        };
+        assert_eq!(exit_code::for_error(&err), exit_code::USAGE);
+        let err = match parse(&["remove", file, "ruSt1"]) {
+            PngMeArgs::Remove(args) => remove(args).unwrap_err(),
+            _ => unreachable!(),
+        };
+        assert_eq!(exit_code::for_error(&err), exit_code::USAGE);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    pub fn test_for_each_chunk() {
+        let path = temp_path("chunks.png");
+        testing_png(&path);
+
+        let mut seen = Vec::new();
+        for_each_chunk(&path, |position, offset, chunk| {
+            seen.push((position, offset, chunk.chunk_type().to_string()));
+            Ok(ControlFlow::Continue(()))
+        })
+        .unwrap();
+        assert_eq!(
+            seen,
+            [(0, 8, "IHDR".to_string()), (1, 33, "IEND".to_string())]
+        );
+
+        let mut visited = 0;
+        for_each_chunk(&path, |_, _, _| {
+            visited += 1;
+            Ok(ControlFlow::Break(()))
+        })
+        .unwrap();
+        assert_eq!(visited, 1);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    pub fn test_default_key_file_only_for_encrypted_payloads() {
+        let path = temp_path("key-file.png");
+        let key = temp_path("key-file.key");
+        let output = temp_path("key-file.txt");
+        let file = path.to_str().unwrap();
+        testing_png(&path);
+        fs::write(&key, "secret\n").unwrap();
+        let config = Config {
+            key_file: Some(key.clone()),
+            ..Config::default()
+        };
+        let run = |args: &[&str]| {
+            let cli = Cli::try_parse_with([&["pngme"], args].concat(), &config).unwrap();
+            match cli.command {
+                PngMeArgs::Encode(args) => encode(args),
+                PngMeArgs::Decode(args) => decode(args, OutputFormat::Text),
+                _ => unreachable!(),
+            }
+        };
+        let decoded = |chunk_type: &str, raw: bool| {
+            let out = output.to_str().unwrap();
+            let mut args = vec!["decode", file, chunk_type, "--output-file", out];
+            if raw {
+                args.push("--raw");
+            }
+            run(&args).unwrap();
+            fs::read(&output).unwrap()
+        };
+
+        let mut png = Png::from_file(&path).unwrap();
+        png.encode_bytes("raWu", b"plain").unwrap();
+        png.encode_payload("enVu", b"plain", false, None).unwrap();
+        fs::write(&path, png.as_bytes()).unwrap();
+        run(&["encode", file, "enVk", "secret payload"]).unwrap();
+
+        assert_eq!(decoded("raWu", true), b"plain");
+        assert_eq!(decoded("enVu", false), b"plain");
+        assert_eq!(decoded("enVk", false), b"secret payload");
+        assert!(matches!(
+            Png::from_file(&path).unwrap().decode_payload("enVk", None),
+            Err(PngmeError::PasswordRequired)
+        ));
+        fs::remove_file(&key).unwrap();
+        assert_eq!(decoded("enVu", false), b"plain");
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&output);
+        let _ = fs::remove_file(path.with_extension("png.bak"));
+    }
+
+    #[test]
+    pub fn test_completions_and_manpage() {
+        let dir = temp_path("generated");
+        let dir_arg = dir.to_str().unwrap();
+
+        match parse(&["completions", "bash", "--dir", dir_arg]) {
+            PngMeArgs::Completions(args) => completions(args).unwrap(),
+            _ => unreachable!(),
+        }
+        match parse(&["manpage", "--dir", dir_arg]) {
+            PngMeArgs::Manpage(args) => manpage(args).unwrap(),
+            _ => unreachable!(),
+        }
+
+        for name in ["pngme.bash", "pngme.1", "pngme-encode.1"] {
+            let generated = fs::read_to_string(dir.join(name)).unwrap();
+            assert!(generated.contains("pngme"), "{} is empty", name);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_write_atomically() {
+        let dir = temp_path("atomic");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("image.png");
+        fs::write(&path, b"original").unwrap();
+
+        // A temporary file in a directory that does not exist cannot be created
+        let missing = dir.join("missing").join(".image.png.tmp");
+        assert!(write_through(&path, &missing, b"replacement").is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"original");
+
+        // A temporary file cannot be renamed over a directory, and is cleaned up
+        let subdir = dir.join("subdir");
+        fs::create_dir(&subdir).unwrap();
+        let temp = dir.join(".subdir.tmp");
+        assert!(write_through(&subdir, &temp, b"replacement").is_err());
+        assert!(!temp.exists());
+        fs::remove_dir(&subdir).unwrap();
+
+        write_output(&path, b"replacement").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"replacement");
+        let names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["image.png"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_back_up() {
+        let path = temp_path("backed-up.png");
+        let backup_path = path.with_extension("png.bak");
+        fs::write(&path, b"before").unwrap();
+
+        back_up(&path, false).unwrap();
+        assert!(!backup_path.exists());
+
+        back_up(&path, true).unwrap();
+        write_output(&path, b"after").unwrap();
+        assert_eq!(fs::read(&backup_path).unwrap(), b"before");
+        assert_eq!(fs::read(&path).unwrap(), b"after");
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&backup_path).unwrap();
+        back_up(&path, true).unwrap();
+        assert!(!backup_path.exists());
+    }
+}
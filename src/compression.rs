@@ -0,0 +1,84 @@
+use std::io::{Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::error::PngmeError;
+use crate::Result;
+
+/// Marks compressed data. Laid out like the start of a zTXt chunk: a keyword, a null separator
+/// and compression method 0 (zlib deflate).
+pub const COMPRESSED_HEADER: &[u8] = b"pngme\0\0";
+
+/// Compresses data with zlib and prefixes it with [`COMPRESSED_HEADER`]
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    deflate_into(COMPRESSED_HEADER.to_vec(), data)
+}
+
+/// Checks whether data starts with [`COMPRESSED_HEADER`]
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(COMPRESSED_HEADER)
+}
+
+/// Decompresses data produced by [`compress`]. Data without the header is returned unchanged.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    if !is_compressed(data) {
+        return Ok(data.to_vec());
+    }
+    inflate(&data[COMPRESSED_HEADER.len()..])
+}
+
+/// Compresses data with zlib, without any header
+pub(crate) fn deflate(data: &[u8]) -> Vec<u8> {
+    deflate_into(Vec::new(), data)
+}
+
+/// Decompresses a zlib stream, without any header
+pub(crate) fn inflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut inflated = Vec::new();
+    ZlibDecoder::new(data)
+        .read_to_end(&mut inflated)
+        .map_err(PngmeError::DecompressionFailed)?;
+    Ok(inflated)
+}
+
+fn deflate_into(buffer: Vec<u8>, data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(buffer, Compression::best());
+    encoder
+        .write_all(data)
+        .expect("writing to a Vec cannot fail");
+    encoder.finish().expect("writing to a Vec cannot fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_round_trip() {
+        let message = "All work and no play makes Jack a dull boy. ".repeat(100);
+        let compressed = compress(message.as_bytes());
+
+        assert!(is_compressed(&compressed));
+        assert!(compressed.len() < message.len());
+        assert_eq!(decompress(&compressed).unwrap(), message.as_bytes());
+    }
+
+    #[test]
+    pub fn test_uncompressed_passthrough() {
+        assert!(!is_compressed(b"plain message"));
+        assert_eq!(decompress(b"plain message").unwrap(), b"plain message");
+    }
+
+    #[test]
+    pub fn test_corrupt_data() {
+        let mut compressed = compress(b"some message");
+        compressed.truncate(COMPRESSED_HEADER.len() + 3);
+
+        assert!(matches!(
+            decompress(&compressed),
+            Err(PngmeError::DecompressionFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+//! Defaults read from a config file, so a team that always hides its watermark under the same
+//! chunk type does not have to repeat it on every command line. The file is
+//! `$XDG_CONFIG_HOME/pngme/config.toml`, falling back to `~/.config/pngme/config.toml`, or the
+//! one given with `--config`:
+//!
+//! ```toml
+//! chunk_type = "ruSt"
+//! compress = true
+//! format = "json"
+//! backup = true
+//! key_file = "/home/me/.config/pngme/key"
+//! ```
+//!
+//! Every setting is optional, and options given on the command line win over it.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use pngme::{PngmeError, Result};
+use toml::{Table, Value};
+
+#[derive(Debug, Default)]
+pub struct Config {
+    /// Chunk type for commands that take one, when it is left out
+    pub chunk_type: Option<String>,
+    /// Whether encode compresses payloads
+    pub compress: Option<bool>,
+    /// Output format, text or json
+    pub format: Option<String>,
+    /// Whether commands that overwrite a file keep a copy of it first
+    pub backup: Option<bool>,
+    /// File to read the passphrase of encode, and of decode for encrypted payloads, from
+    pub key_file: Option<PathBuf>,
+}
+
+impl Config {
+    /// Reads the config file given with --config, or the default one if it exists
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        tracing::debug!(path = %path.display(), "reading config");
+        let contents = fs::read_to_string(&path).map_err(|err| {
+            PngmeError::Io(io::Error::new(
+                err.kind(),
+                format!("{}: {}", path.display(), err),
+            ))
+        })?;
+        Config::parse(&contents).map_err(|message| {
+            PngmeError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), message),
+            ))
+        })
+    }
+
+    fn parse(contents: &str) -> std::result::Result<Config, String> {
+        let table: Table = contents
+            .parse()
+            .map_err(|err: toml::de::Error| err.message().to_string())?;
+        let mut config = Config::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "chunk_type" => config.chunk_type = Some(string(&key, value)?),
+                "compress" => config.compress = Some(boolean(&key, value)?),
+                "format" => config.format = Some(string(&key, value)?),
+                "backup" => config.backup = Some(boolean(&key, value)?),
+                "key_file" => config.key_file = Some(string(&key, value)?.into()),
+                _ => return Err(format!("unknown setting {}", key)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(dir.join("pngme").join("config.toml"))
+}
+
+fn string(key: &str, value: Value) -> std::result::Result<String, String> {
+    match value {
+        Value::String(value) => Ok(value),
+        other => Err(format!(
+            "{} must be a string, not {}",
+            key,
+            other.type_str()
+        )),
+    }
+}
+
+fn boolean(key: &str, value: Value) -> std::result::Result<bool, String> {
+    match value {
+        Value::Boolean(value) => Ok(value),
+        other => Err(format!(
+            "{} must be true or false, not {}",
+            key,
+            other.type_str()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse() {
+        let config = Config::parse(
+            "chunk_type = \"ruSt\"\ncompress = true\nformat = \"json\"\nbackup = false\nkey_file = \"key\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.chunk_type.as_deref(), Some("ruSt"));
+        assert_eq!(config.compress, Some(true));
+        assert_eq!(config.format.as_deref(), Some("json"));
+        assert_eq!(config.backup, Some(false));
+        assert_eq!(config.key_file, Some(PathBuf::from("key")));
+
+        let empty = Config::parse("").unwrap();
+        assert!(empty.chunk_type.is_none() && empty.key_file.is_none());
+    }
+
+    #[test]
+    pub fn test_parse_errors() {
+        assert_eq!(
+            Config::parse("colour = \"red\"").unwrap_err(),
+            "unknown setting colour"
+        );
+        assert_eq!(
+            Config::parse("compress = \"yes\"").unwrap_err(),
+            "compress must be true or false, not string"
+        );
+        assert!(Config::parse("chunk_type = ").is_err());
+        assert!(Config::parse("[chunk_type").is_err());
+    }
+
+    #[test]
+    pub fn test_load() {
+        let path = env::temp_dir().join(format!("pngme-config-{}.toml", std::process::id()));
+        fs::write(&path, "format = \"json\"\n").unwrap();
+        let config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.format.as_deref(), Some("json"));
+
+        fs::write(&path, "format = json\n").unwrap();
+        let err = Config::load(Some(&path)).unwrap_err().to_string();
+        assert!(err.starts_with(&path.display().to_string()), "{}", err);
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            Config::load(Some(&path)),
+            Err(PngmeError::Io(err)) if err.kind() == io::ErrorKind::NotFound
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use crate::error::PngmeError;
+use crate::Result;
+
+const SALT_BYTES: usize = 16;
+const NONCE_BYTES: usize = 12;
+const KEY_BYTES: usize = 32;
+const TAG_BYTES: usize = 16;
+
+/// Number of bytes [`encrypt`] adds to the plaintext: the salt, the nonce and the
+/// authentication tag
+pub const OVERHEAD_BYTES: usize = SALT_BYTES + NONCE_BYTES + TAG_BYTES;
+
+/// Encrypts data with a key derived from a passphrase.
+///
+/// The key is derived with Argon2id from the passphrase and a random salt, and the data is sealed
+/// with ChaCha20-Poly1305 under a random nonce. The output is laid out as salt, nonce, ciphertext.
+pub fn encrypt(plaintext: &[u8], password: &str) -> Result<Vec<u8>> {
+    let mut salt = [0; SALT_BYTES];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = cipher(password, &salt)?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .expect("ChaCha20-Poly1305 accepts messages far larger than a chunk");
+
+    Ok(salt
+        .iter()
+        .chain(nonce.iter())
+        .chain(ciphertext.iter())
+        .copied()
+        .collect())
+}
+
+/// Decrypts data produced by [`encrypt`], failing if the passphrase is wrong or the data was
+/// tampered with
+pub fn decrypt(data: &[u8], password: &str) -> Result<Vec<u8>> {
+    if data.len() < SALT_BYTES + NONCE_BYTES {
+        return Err(PngmeError::DecryptionFailed);
+    }
+    let (salt, rest) = data.split_at(SALT_BYTES);
+    let (nonce, ciphertext) = rest.split_at(NONCE_BYTES);
+    cipher(password, salt)?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| PngmeError::DecryptionFailed)
+}
+
+fn cipher(password: &str, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+    let mut key = [0; KEY_BYTES];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|err| PngmeError::KeyDerivation(err.to_string()))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_overhead() {
+        let encrypted = encrypt(b"Message", "hunter2").unwrap();
+        assert_eq!(encrypted.len(), b"Message".len() + OVERHEAD_BYTES);
+    }
+
+    #[test]
+    pub fn test_round_trip() {
+        let encrypted = encrypt(b"secret message", "hunter2").unwrap();
+        assert_eq!(decrypt(&encrypted, "hunter2").unwrap(), b"secret message");
+    }
+
+    #[test]
+    pub fn test_ciphertext_hides_plaintext() {
+        let encrypted = encrypt(b"secret message", "hunter2").unwrap();
+        assert_eq!(encrypted.len(), SALT_BYTES + NONCE_BYTES + 14 + 16);
+        assert!(!encrypted
+            .windows(b"secret".len())
+            .any(|window| window == b"secret"));
+    }
+
+    #[test]
+    pub fn test_wrong_password() {
+        let encrypted = encrypt(b"secret message", "hunter2").unwrap();
+        assert!(matches!(
+            decrypt(&encrypted, "hunter3"),
+            Err(PngmeError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    pub fn test_tampered_data() {
+        let mut encrypted = encrypt(b"secret message", "hunter2").unwrap();
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        assert!(decrypt(&encrypted, "hunter2").is_err());
+        assert!(decrypt(&encrypted[..10], "hunter2").is_err());
+    }
+}
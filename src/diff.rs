@@ -0,0 +1,358 @@
+use std::fmt::Display;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// Where a chunk sits in one of the two PNGs being compared, and what it holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkState {
+    /// Position of the chunk among all chunks in its PNG
+    pub index: usize,
+    /// Byte offset of the chunk's length field from the start of the file
+    pub offset: usize,
+    /// Number of bytes in the chunk's data field
+    pub length: u32,
+    /// The CRC stored for the chunk
+    pub crc: u32,
+}
+
+/// A difference between the chunks of two PNGs
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "change", rename_all = "snake_case"))]
+pub enum ChunkChange {
+    /// A chunk that only the second PNG has
+    Added {
+        chunk_type: ChunkType,
+        after: ChunkState,
+    },
+    /// A chunk that only the first PNG has
+    Removed {
+        chunk_type: ChunkType,
+        before: ChunkState,
+    },
+    /// A chunk whose data or CRC differs between the two PNGs, while the chunks around it match
+    Modified {
+        chunk_type: ChunkType,
+        before: ChunkState,
+        after: ChunkState,
+    },
+}
+
+impl ChunkChange {
+    /// A stable, machine-readable name for the kind of change
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ChunkChange::Added { .. } => "added",
+            ChunkChange::Removed { .. } => "removed",
+            ChunkChange::Modified { .. } => "modified",
+        }
+    }
+
+    /// The chunk type of the changed chunk
+    pub fn chunk_type(&self) -> &ChunkType {
+        match self {
+            ChunkChange::Added { chunk_type, .. }
+            | ChunkChange::Removed { chunk_type, .. }
+            | ChunkChange::Modified { chunk_type, .. } => chunk_type,
+        }
+    }
+
+    /// The chunk in the first PNG, unless it was added
+    pub fn before(&self) -> Option<&ChunkState> {
+        match self {
+            ChunkChange::Added { .. } => None,
+            ChunkChange::Removed { before, .. } | ChunkChange::Modified { before, .. } => {
+                Some(before)
+            }
+        }
+    }
+
+    /// The chunk in the second PNG, unless it was removed
+    pub fn after(&self) -> Option<&ChunkState> {
+        match self {
+            ChunkChange::Removed { .. } => None,
+            ChunkChange::Added { after, .. } | ChunkChange::Modified { after, .. } => Some(after),
+        }
+    }
+}
+
+impl Display for ChunkChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkChange::Added { chunk_type, after } => write!(
+                f,
+                "added {} chunk #{} at offset {}, {} data bytes",
+                chunk_type, after.index, after.offset, after.length
+            ),
+            ChunkChange::Removed { chunk_type, before } => write!(
+                f,
+                "removed {} chunk #{} at offset {}, {} data bytes",
+                chunk_type, before.index, before.offset, before.length
+            ),
+            ChunkChange::Modified {
+                chunk_type,
+                before,
+                after,
+            } => {
+                write!(
+                    f,
+                    "modified {} chunk #{} -> #{} at offset {} -> {}, ",
+                    chunk_type, before.index, after.index, before.offset, after.offset
+                )?;
+                write!(
+                    f,
+                    "{} -> {} data bytes, crc {:08x} -> {:08x}",
+                    before.length, after.length, before.crc, after.crc
+                )
+            }
+        }
+    }
+}
+
+/// Lists the chunks that were added, removed or modified to get from `before` to `after`.
+///
+/// Chunks count as equal when their serialized bytes match, and the list is kept as short as
+/// possible by matching up the longest common run of chunks. Where chunks were removed and added
+/// between the same two matching chunks, a removed and an added chunk of the same type are
+/// reported as one modified chunk. Removals and modifications come before additions at the same
+/// spot.
+pub fn diff(before: &Png, after: &Png) -> Vec<ChunkChange> {
+    let old = before.chunks();
+    let new = after.chunks();
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| same_chunk(old, new))
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| same_chunk(old, new))
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    // common[i][j] is the length of the longest common subsequence of old_middle[i..] and
+    // new_middle[j..]
+    let mut common = vec![vec![0usize; new_middle.len() + 1]; old_middle.len() + 1];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            common[i][j] = match same_chunk(&old_middle[i], &new_middle[j]) {
+                true => common[i + 1][j + 1] + 1,
+                false => common[i + 1][j].max(common[i][j + 1]),
+            };
+        }
+    }
+
+    let mut hunk = Hunk {
+        old,
+        new,
+        old_states: states(old),
+        new_states: states(new),
+        removed: Vec::new(),
+        added: Vec::new(),
+        changes: Vec::new(),
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() || j < new_middle.len() {
+        if i < old_middle.len()
+            && j < new_middle.len()
+            && same_chunk(&old_middle[i], &new_middle[j])
+        {
+            hunk.flush();
+            i += 1;
+            j += 1;
+        } else if j == new_middle.len()
+            || (i < old_middle.len() && common[i + 1][j] >= common[i][j + 1])
+        {
+            hunk.removed.push(prefix + i);
+            i += 1;
+        } else {
+            hunk.added.push(prefix + j);
+            j += 1;
+        }
+    }
+    hunk.flush();
+    hunk.changes
+}
+
+/// The chunks removed and added between two matching chunks, and the changes found so far
+struct Hunk<'a> {
+    old: &'a [Chunk],
+    new: &'a [Chunk],
+    old_states: Vec<ChunkState>,
+    new_states: Vec<ChunkState>,
+    removed: Vec<usize>,
+    added: Vec<usize>,
+    changes: Vec<ChunkChange>,
+}
+
+impl Hunk<'_> {
+    /// Turns the pending removals and additions into changes, pairing each removed chunk with
+    /// the next added chunk of the same type, so that the pairs keep their order
+    fn flush(&mut self) {
+        let mut paired = vec![false; self.added.len()];
+        let mut next = 0;
+        for &i in &self.removed {
+            let chunk_type = self.old[i].chunk_type().clone();
+            let before = self.old_states[i];
+            let partner = self.added[next..]
+                .iter()
+                .position(|&j| *self.new[j].chunk_type() == chunk_type)
+                .map(|k| next + k);
+            self.changes.push(match partner {
+                Some(k) => {
+                    paired[k] = true;
+                    next = k + 1;
+                    ChunkChange::Modified {
+                        chunk_type,
+                        before,
+                        after: self.new_states[self.added[k]],
+                    }
+                }
+                None => ChunkChange::Removed { chunk_type, before },
+            });
+        }
+        for (k, &j) in self.added.iter().enumerate() {
+            if !paired[k] {
+                self.changes.push(ChunkChange::Added {
+                    chunk_type: self.new[j].chunk_type().clone(),
+                    after: self.new_states[j],
+                });
+            }
+        }
+        self.removed.clear();
+        self.added.clear();
+    }
+}
+
+fn same_chunk(a: &Chunk, b: &Chunk) -> bool {
+    a.chunk_type() == b.chunk_type() && a.crc() == b.crc() && a.data() == b.data()
+}
+
+/// The position and contents of every chunk, counting the signature in the offsets
+fn states(chunks: &[Chunk]) -> Vec<ChunkState> {
+    chunks
+        .iter()
+        .enumerate()
+        .scan(Png::STANDARD_HEADER.len(), |offset, (index, chunk)| {
+            let state = ChunkState {
+                index,
+                offset: *offset,
+                length: chunk.length(),
+                crc: chunk.crc(),
+            };
+            *offset += Chunk::METADATA_BYTES + chunk.data().len();
+            Some(state)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &str) -> Chunk {
+        Chunk::new(
+            ChunkType::from_str(chunk_type).unwrap(),
+            data.as_bytes().to_vec(),
+        )
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", "header"),
+            chunk("IDAT", "pixels"),
+            chunk("IEND", ""),
+        ])
+    }
+
+    #[test]
+    pub fn test_no_changes() {
+        assert!(diff(&testing_png(), &testing_png()).is_empty());
+    }
+
+    #[test]
+    pub fn test_added_chunk() {
+        let mut after = testing_png();
+        after.encode_bytes("ruSt", b"hi").unwrap();
+
+        assert_eq!(
+            diff(&testing_png(), &after),
+            vec![ChunkChange::Added {
+                chunk_type: ChunkType::from_str("ruSt").unwrap(),
+                after: ChunkState {
+                    index: 2,
+                    offset: 8 + 18 + 18,
+                    length: 2,
+                    crc: after.chunks()[2].crc(),
+                },
+            }]
+        );
+    }
+
+    #[test]
+    pub fn test_replaced_chunks() {
+        let before = Png::from_chunks(vec![
+            chunk("IHDR", "header"),
+            chunk("ruSt", "one"),
+            chunk("IDAT", "pixels"),
+            chunk("teSt", "two"),
+            chunk("IEND", ""),
+        ]);
+        let after = Png::from_chunks(vec![
+            chunk("IHDR", "header"),
+            chunk("IDAT", "pixels"),
+            chunk("teSt", "three"),
+            chunk("IEND", ""),
+        ]);
+
+        let changes: Vec<String> = diff(&before, &after)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                "removed ruSt chunk #1 at offset 26, 3 data bytes",
+                "modified teSt chunk #3 -> #2 at offset 59 -> 44, 3 -> 5 data bytes, crc a817640b \
+                 -> d124bbbd",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_unpaired_changes() {
+        let before = Png::from_chunks(vec![
+            chunk("IHDR", "header"),
+            chunk("teSt", "one"),
+            chunk("ruSt", "two"),
+            chunk("IEND", ""),
+        ]);
+        let after = Png::from_chunks(vec![
+            chunk("IHDR", "header"),
+            chunk("ruSt", "three"),
+            chunk("abCd", "four"),
+            chunk("IEND", ""),
+        ]);
+
+        let changes = diff(&before, &after);
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(changes[0], ChunkChange::Removed { before, .. } if before.index == 1));
+        assert!(matches!(
+            changes[1],
+            ChunkChange::Modified { before, after, .. } if before.index == 2 && after.index == 1
+        ));
+        assert!(matches!(changes[2], ChunkChange::Added { after, .. } if after.index == 2));
+        let kinds: Vec<&str> = changes.iter().map(ChunkChange::kind).collect();
+        assert_eq!(kinds, ["removed", "modified", "added"]);
+        assert_eq!(changes[1].chunk_type().to_string(), "ruSt");
+        assert_eq!(changes[0].after(), None);
+        assert_eq!(changes[2].after().unwrap().length, 4);
+    }
+}
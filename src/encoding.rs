@@ -0,0 +1,115 @@
+//! How message text on the command line maps to payload bytes. Binary payloads printed as they
+//! are can mangle the terminal, so encode and decode can spell them in base64 or hex instead.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use pngme::{PngmeError, Result};
+
+use crate::args::TextEncoding;
+
+/// Spells payload bytes as text
+pub fn to_text(bytes: &[u8], encoding: TextEncoding) -> Result<String> {
+    match encoding {
+        TextEncoding::Utf8 => Ok(String::from_utf8(bytes.to_vec())?),
+        TextEncoding::Base64 => Ok(STANDARD.encode(bytes)),
+        TextEncoding::Hex => Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect()),
+    }
+}
+
+/// Reads payload bytes back from text spelled with [`to_text`]. Whitespace is ignored in base64
+/// and hex, so wrapped output can be pasted back in.
+pub fn from_text(text: &str, encoding: TextEncoding) -> Result<Vec<u8>> {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    match encoding {
+        TextEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+        TextEncoding::Base64 => STANDARD
+            .decode(compact)
+            .map_err(|err| PngmeError::InvalidArgument(format!("invalid base64: {}", err))),
+        TextEncoding::Hex => {
+            if !compact.len().is_multiple_of(2) {
+                return Err(PngmeError::InvalidArgument(
+                    "hex has an odd number of digits".to_string(),
+                ));
+            }
+            compact
+                .as_bytes()
+                .chunks(2)
+                .enumerate()
+                .map(|(i, pair)| match pair.iter().all(u8::is_ascii_hexdigit) {
+                    true => Ok(u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap()),
+                    false => Err(PngmeError::InvalidArgument(format!(
+                        "invalid hex at byte {}",
+                        i
+                    ))),
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exit_code;
+
+    #[test]
+    pub fn test_round_trips() {
+        let bytes = b"\x00\xffhi\n".to_vec();
+        for encoding in [TextEncoding::Base64, TextEncoding::Hex] {
+            let text = to_text(&bytes, encoding).unwrap();
+            assert_eq!(from_text(&text, encoding).unwrap(), bytes);
+        }
+        assert_eq!(to_text(&bytes, TextEncoding::Hex).unwrap(), "00ff68690a");
+        assert_eq!(to_text(&bytes, TextEncoding::Base64).unwrap(), "AP9oaQo=");
+
+        let text = "héllo wörld";
+        let utf8 = from_text(text, TextEncoding::Utf8).unwrap();
+        assert_eq!(to_text(&utf8, TextEncoding::Utf8).unwrap(), text);
+    }
+
+    #[test]
+    pub fn test_whitespace_is_ignored() {
+        assert_eq!(
+            from_text("00 ff\n68 69", TextEncoding::Hex).unwrap(),
+            b"\x00\xffhi"
+        );
+        assert_eq!(
+            from_text("AP9o\naQo=", TextEncoding::Base64).unwrap(),
+            b"\x00\xffhi\n"
+        );
+        assert_eq!(from_text(" a ", TextEncoding::Utf8).unwrap(), b" a ");
+    }
+
+    #[test]
+    pub fn test_invalid_text_is_a_usage_error() {
+        for (text, encoding) in [
+            ("abc", TextEncoding::Hex),
+            ("zz", TextEncoding::Hex),
+            ("not base64!", TextEncoding::Base64),
+        ] {
+            let err = from_text(text, encoding).unwrap_err();
+            assert!(
+                matches!(err, PngmeError::InvalidArgument(_)),
+                "{}: {:?}",
+                text,
+                err
+            );
+            assert_eq!(exit_code::for_error(&err), exit_code::USAGE);
+        }
+        assert_eq!(
+            from_text("00zz", TextEncoding::Hex)
+                .unwrap_err()
+                .to_string(),
+            PngmeError::InvalidArgument("invalid hex at byte 1".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_invalid_utf8() {
+        assert!(matches!(
+            to_text(b"\xff\xfe", TextEncoding::Utf8),
+            Err(PngmeError::InvalidUtf8(_))
+        ));
+        assert_eq!(to_text(b"\xff\xfe", TextEncoding::Hex).unwrap(), "fffe");
+    }
+}
@@ -0,0 +1,303 @@
+use crc::{Crc, CRC_32_ISO_HDLC};
+
+use crate::compression;
+use crate::crypto;
+use crate::error::PngmeError;
+use crate::Result;
+
+/// Marks a payload wrapped in an [`Envelope`]
+pub const ENVELOPE_MAGIC: &[u8; 4] = b"pENV";
+
+/// The newest envelope version this crate reads and the one it writes
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// Size of the envelope header without the file name: magic, version, flags, file name length,
+/// payload length and checksum
+pub const ENVELOPE_HEADER_BYTES: usize = 15;
+
+/// Flag bit set when the payload was compressed with zlib
+pub const FLAG_COMPRESSED: u8 = 0b01;
+
+/// Flag bit set when the payload was encrypted with [`crypto::encrypt`]
+pub const FLAG_ENCRYPTED: u8 = 0b10;
+
+const KNOWN_FLAGS: u8 = FLAG_COMPRESSED | FLAG_ENCRYPTED;
+const CHECKSUM: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// A payload together with a record of how it was stored, so it can be read back without
+/// guessing.
+///
+/// An envelope is laid out as [`ENVELOPE_MAGIC`], the version, the flags, the length of the file
+/// name (a `u8`), the UTF-8 file name, then the payload length and the CRC-32 of the payload (both
+/// big-endian `u32`s) and finally the payload itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    /// Version of the envelope format
+    pub version: u8,
+    /// Whether the payload is compressed
+    pub compressed: bool,
+    /// Whether the payload is encrypted
+    pub encrypted: bool,
+    /// Name of the file the data came from, if any
+    pub filename: Option<String>,
+    /// The stored bytes, after compression and encryption
+    pub payload: Vec<u8>,
+}
+
+impl Envelope {
+    /// Compresses and encrypts data as asked and wraps the result in an envelope. Compression
+    /// happens first, since encrypted data does not compress.
+    pub fn seal(
+        data: &[u8],
+        filename: Option<&str>,
+        compress: bool,
+        password: Option<&str>,
+    ) -> Result<Envelope> {
+        if filename.is_some_and(|name| name.len() > u8::MAX as usize) {
+            return Err(PngmeError::InvalidEnvelope(format!(
+                "file names are limited to {} bytes",
+                u8::MAX
+            )));
+        }
+        let payload = match compress {
+            true => compression::deflate(data),
+            false => data.to_vec(),
+        };
+        let payload = match password {
+            Some(password) => crypto::encrypt(&payload, password)?,
+            None => payload,
+        };
+        check_payload_length(payload.len())?;
+        Ok(Envelope {
+            version: ENVELOPE_VERSION,
+            compressed: compress,
+            encrypted: password.is_some(),
+            filename: filename.map(str::to_string),
+            payload,
+        })
+    }
+
+    /// Decrypts and decompresses the payload, returning the original data. Fails with
+    /// [`PngmeError::PasswordRequired`] if the payload is encrypted and no passphrase is given.
+    pub fn open(&self, password: Option<&str>) -> Result<Vec<u8>> {
+        let data = match (self.encrypted, password) {
+            (true, Some(password)) => crypto::decrypt(&self.payload, password)?,
+            (true, None) => return Err(PngmeError::PasswordRequired),
+            (false, _) => self.payload.clone(),
+        };
+        match self.compressed {
+            true => compression::inflate(&data),
+            false => Ok(data),
+        }
+    }
+
+    /// Serializes the envelope, header first
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let filename = self.filename.as_deref().unwrap_or_default().as_bytes();
+        let mut flags = 0;
+        if self.compressed {
+            flags |= FLAG_COMPRESSED;
+        }
+        if self.encrypted {
+            flags |= FLAG_ENCRYPTED;
+        }
+        ENVELOPE_MAGIC
+            .iter()
+            .copied()
+            .chain([self.version, flags, filename.len() as u8])
+            .chain(filename.iter().copied())
+            .chain((self.payload.len() as u32).to_be_bytes())
+            .chain(CHECKSUM.checksum(&self.payload).to_be_bytes())
+            .chain(self.payload.iter().copied())
+            .collect()
+    }
+
+    /// Parses an envelope, checking its version, flags, length and checksum
+    pub fn from_bytes(bytes: &[u8]) -> Result<Envelope> {
+        let invalid = |reason: &str| PngmeError::InvalidEnvelope(reason.to_string());
+        if !is_envelope(bytes) {
+            return Err(invalid("missing envelope header"));
+        }
+        let version = bytes[4];
+        if version == 0 || version > ENVELOPE_VERSION {
+            return Err(PngmeError::InvalidEnvelope(format!(
+                "version {} is not supported, this pngme reads up to version {}",
+                version, ENVELOPE_VERSION
+            )));
+        }
+        let flags = bytes[5];
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(PngmeError::InvalidEnvelope(format!(
+                "unknown flags {:#04x}",
+                flags & !KNOWN_FLAGS
+            )));
+        }
+        let name_end = 7 + bytes[6] as usize;
+        if bytes.len() < name_end + 8 {
+            return Err(invalid("truncated header"));
+        }
+        let filename = match &bytes[7..name_end] {
+            [] => None,
+            name => Some(
+                String::from_utf8(name.to_vec())
+                    .map_err(|_| invalid("file name is not valid UTF-8"))?,
+            ),
+        };
+        let length = u32::from_be_bytes(bytes[name_end..name_end + 4].try_into().unwrap());
+        let checksum = u32::from_be_bytes(bytes[name_end + 4..name_end + 8].try_into().unwrap());
+        let payload = &bytes[name_end + 8..];
+        if payload.len() != length as usize {
+            return Err(PngmeError::InvalidEnvelope(format!(
+                "{} payload bytes declared, {} stored",
+                length,
+                payload.len()
+            )));
+        }
+        if CHECKSUM.checksum(payload) != checksum {
+            return Err(invalid("payload checksum does not match"));
+        }
+
+        Ok(Envelope {
+            version,
+            compressed: flags & FLAG_COMPRESSED != 0,
+            encrypted: flags & FLAG_ENCRYPTED != 0,
+            filename,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// Checks that a payload of this many bytes fits the envelope's `u32` length field
+fn check_payload_length(length: usize) -> Result<()> {
+    match u32::try_from(length) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(PngmeError::InvalidEnvelope(format!(
+            "payloads are limited to {} bytes, this one is {}",
+            u32::MAX,
+            length
+        ))),
+    }
+}
+
+/// Checks whether data starts with an envelope header
+pub fn is_envelope(data: &[u8]) -> bool {
+    data.len() >= ENVELOPE_HEADER_BYTES && data.starts_with(ENVELOPE_MAGIC)
+}
+
+/// Reads back a stored payload, along with the file name recorded in its envelope. Payloads
+/// without an envelope are read with [`open_bare`].
+pub fn open_payload(payload: &[u8], password: Option<&str>) -> Result<(Vec<u8>, Option<String>)> {
+    if !is_envelope(payload) {
+        return Ok((open_bare(payload, password)?, None));
+    }
+    let envelope = Envelope::from_bytes(payload)?;
+    Ok((envelope.open(password)?, envelope.filename))
+}
+
+/// Reads back a payload stored without an envelope, by `encode --raw` or versions of pngme from
+/// before envelopes. It is decrypted if a passphrase is given and decompressed if it carries
+/// [`compression::COMPRESSED_HEADER`].
+pub fn open_bare(payload: &[u8], password: Option<&str>) -> Result<Vec<u8>> {
+    match password {
+        Some(password) => compression::decompress(&crypto::decrypt(payload, password)?),
+        None => compression::decompress(payload),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_round_trip() {
+        let message = "All work and no play makes Jack a dull boy. ".repeat(100);
+        let envelope =
+            Envelope::seal(message.as_bytes(), Some("jack.txt"), true, Some("hunter2")).unwrap();
+        let bytes = envelope.to_bytes();
+
+        assert!(is_envelope(&bytes));
+        assert!(bytes.len() < message.len());
+        let parsed = Envelope::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, envelope);
+        assert_eq!(parsed.filename.as_deref(), Some("jack.txt"));
+        assert!(parsed.compressed && parsed.encrypted);
+        assert_eq!(parsed.open(Some("hunter2")).unwrap(), message.as_bytes());
+        assert!(matches!(
+            parsed.open(None),
+            Err(PngmeError::PasswordRequired)
+        ));
+    }
+
+    #[test]
+    pub fn test_plain_payload() {
+        let envelope = Envelope::seal(b"hello", None, false, None).unwrap();
+        let bytes = envelope.to_bytes();
+
+        assert_eq!(bytes.len(), ENVELOPE_HEADER_BYTES + 5);
+        assert!(bytes.ends_with(b"hello"));
+        let parsed = Envelope::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.filename, None);
+        assert_eq!(parsed.open(None).unwrap(), b"hello");
+        assert!(!is_envelope(b"hello"));
+    }
+
+    #[test]
+    pub fn test_open_payload() {
+        let sealed = Envelope::seal(b"hello", Some("a.txt"), true, Some("secret"))
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            open_payload(&sealed, Some("secret")).unwrap(),
+            (b"hello".to_vec(), Some("a.txt".to_string()))
+        );
+        assert!(matches!(
+            open_payload(&sealed, None),
+            Err(PngmeError::PasswordRequired)
+        ));
+
+        let bare = crypto::encrypt(&compression::compress(b"hello"), "secret").unwrap();
+        assert_eq!(open_payload(&bare, Some("secret")).unwrap().0, b"hello");
+        assert_eq!(
+            open_payload(b"hello", None).unwrap(),
+            (b"hello".to_vec(), None)
+        );
+    }
+
+    #[test]
+    pub fn test_payload_length_limit() {
+        assert!(check_payload_length(u32::MAX as usize).is_ok());
+        // Only 64-bit targets can hold a payload too long for the length field
+        if let Some(length) = (u32::MAX as usize).checked_add(1) {
+            assert!(matches!(
+                check_payload_length(length),
+                Err(PngmeError::InvalidEnvelope(_))
+            ));
+        }
+    }
+
+    #[test]
+    pub fn test_invalid_envelope() {
+        let bytes = Envelope::seal(b"hello", None, false, None)
+            .unwrap()
+            .to_bytes();
+
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            Envelope::from_bytes(&corrupt),
+            Err(PngmeError::InvalidEnvelope(reason)) if reason.contains("checksum")
+        ));
+
+        let mut newer = bytes.clone();
+        newer[4] = ENVELOPE_VERSION + 1;
+        assert!(matches!(
+            Envelope::from_bytes(&newer),
+            Err(PngmeError::InvalidEnvelope(reason)) if reason.contains("version")
+        ));
+
+        let mut flagged = bytes.clone();
+        flagged[5] |= 0b100;
+        assert!(Envelope::from_bytes(&flagged).is_err());
+        assert!(Envelope::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt::Display;
+use std::io;
+use std::string::FromUtf8Error;
+
+use crate::chunk_type::ChunkType;
+
+/// Everything that can go wrong while reading, editing or writing a PNG
+#[derive(Debug)]
+pub enum PngmeError {
+    /// A chunk type byte was not an ASCII letter
+    InvalidChunkType { byte: u8, position: usize },
+    /// A chunk type was not exactly four bytes long
+    InvalidChunkTypeLength(usize),
+    /// A chunk's stored CRC does not match the CRC of its type and data
+    InvalidCrc {
+        chunk_type: ChunkType,
+        expected: u32,
+        actual: u32,
+    },
+    /// A chunk's length field does not match the bytes that follow it
+    InvalidChunkLength { declared: u32, available: usize },
+    /// The input ended in the middle of a chunk
+    TruncatedChunk { offset: usize },
+    /// The input does not start with the PNG signature
+    MissingSignature,
+    /// No chunk of the requested type exists
+    ChunkNotFound(String),
+    /// A chunk index is past the end of the chunk list
+    IndexOutOfRange { index: usize, len: usize },
+    /// Chunk data was expected to be UTF-8 but is not
+    InvalidUtf8(FromUtf8Error),
+    /// A key could not be derived from the passphrase
+    KeyDerivation(String),
+    /// Encrypted data could not be decrypted, because the passphrase is wrong or the data was
+    /// tampered with
+    DecryptionFailed,
+    /// Compressed data could not be decompressed
+    DecompressionFailed(io::Error),
+    /// A payload split across several chunks is missing one of its parts
+    MissingSegment { index: u32, count: u32 },
+    /// The parts of a split payload do not fit together
+    InvalidSegment(String),
+    /// The envelope around a payload is malformed, corrupt or from a newer version of pngme
+    InvalidEnvelope(String),
+    /// A payload is encrypted but no passphrase was given to decrypt it
+    PasswordRequired,
+    /// The IHDR chunk does not describe a valid image
+    InvalidHeader(String),
+    /// The image cannot carry data in its pixels
+    UnsupportedImage(String),
+    /// The IDAT chunks do not hold valid image data
+    InvalidImageData(String),
+    /// A payload does not fit in the space available for it
+    PayloadTooLarge { size: usize, capacity: usize },
+    /// A signing or verifying key could not be read
+    InvalidKey(String),
+    /// A payload does not match its signature
+    InvalidSignature,
+    /// A payload does not match its HMAC, because it was changed or the passphrase is wrong
+    InvalidHmac,
+    /// No signature is stored for the payload under this chunk type
+    SignatureNotFound(String),
+    /// The manifest of named payloads is malformed or points at a chunk that is gone
+    InvalidManifest(String),
+    /// No payload is stored under the requested name
+    PayloadNotFound(String),
+    /// The acTL, fcTL and fdAT chunks of an APNG do not describe a valid animation
+    InvalidAnimation(String),
+    /// A tEXt, zTXt or iTXt keyword breaks the PNG keyword rules
+    InvalidKeyword(String),
+    /// A tEXt, zTXt or iTXt chunk is malformed
+    InvalidTextChunk(String),
+    /// No textual chunk has the requested keyword
+    KeywordNotFound(String),
+    /// The data from `offset` on could not be read as chunks
+    UnreadableData { offset: usize, reason: String },
+    /// Validation found problems with a PNG
+    ValidationFailed { issues: usize },
+    /// An operation applied to many files failed for some of them
+    BatchFailed { failed: usize, total: usize },
+    /// An argument is malformed, whether passed on the command line or through the C interface
+    InvalidArgument(String),
+    /// Reading or writing failed
+    Io(io::Error),
+}
+
+impl Display for PngmeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PngmeError::InvalidChunkType { byte, position } => write!(
+                f,
+                "invalid chunk type: byte {} ({:#04x}) is not an ASCII letter",
+                position, byte
+            ),
+            PngmeError::InvalidChunkTypeLength(length) => {
+                write!(f, "invalid chunk type: expected 4 bytes, got {}", length)
+            }
+            PngmeError::InvalidCrc {
+                chunk_type,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "invalid CRC for chunk {}: expected {:#010x}, got {:#010x}",
+                chunk_type, expected, actual
+            ),
+            PngmeError::InvalidChunkLength {
+                declared,
+                available,
+            } => write!(
+                f,
+                "invalid chunk length: {} data bytes declared, {} available",
+                declared, available
+            ),
+            PngmeError::TruncatedChunk { offset } => {
+                write!(f, "truncated chunk at offset {}", offset)
+            }
+            PngmeError::MissingSignature => write!(f, "not a PNG file: missing PNG signature"),
+            PngmeError::ChunkNotFound(chunk_type) => write!(f, "chunk {} not found", chunk_type),
+            PngmeError::IndexOutOfRange { index, len } => write!(
+                f,
+                "chunk index {} is out of range for a PNG with {} chunks",
+                index, len
+            ),
+            PngmeError::InvalidUtf8(err) => write!(f, "chunk data is not valid UTF-8: {}", err),
+            PngmeError::KeyDerivation(err) => write!(f, "key derivation failed: {}", err),
+            PngmeError::DecryptionFailed => write!(
+                f,
+                "decryption failed: wrong password or the data was tampered with"
+            ),
+            PngmeError::DecompressionFailed(err) => write!(f, "decompression failed: {}", err),
+            PngmeError::MissingSegment { index, count } => {
+                write!(f, "payload part {} of {} is missing", index + 1, count)
+            }
+            PngmeError::InvalidSegment(reason) => write!(f, "invalid payload parts: {}", reason),
+            PngmeError::InvalidEnvelope(reason) => {
+                write!(f, "invalid payload envelope: {}", reason)
+            }
+            PngmeError::PasswordRequired => {
+                write!(
+                    f,
+                    "the payload is encrypted, a password is needed to read it"
+                )
+            }
+            PngmeError::InvalidHeader(reason) => write!(f, "invalid IHDR chunk: {}", reason),
+            PngmeError::UnsupportedImage(reason) => write!(f, "unsupported image: {}", reason),
+            PngmeError::InvalidImageData(reason) => write!(f, "invalid image data: {}", reason),
+            PngmeError::PayloadTooLarge { size, capacity } => write!(
+                f,
+                "payload of {} bytes does not fit, the image can hold at most {}",
+                size, capacity
+            ),
+            PngmeError::InvalidKey(reason) => write!(f, "invalid key: {}", reason),
+            PngmeError::InvalidSignature => write!(
+                f,
+                "signature check failed: the payload was tampered with or signed with another key"
+            ),
+            PngmeError::InvalidHmac => write!(
+                f,
+                "HMAC check failed: the payload was tampered with or the key is wrong"
+            ),
+            PngmeError::SignatureNotFound(chunk_type) => {
+                write!(f, "no signature found for chunk {}", chunk_type)
+            }
+            PngmeError::InvalidManifest(reason) => {
+                write!(f, "invalid payload manifest: {}", reason)
+            }
+            PngmeError::PayloadNotFound(name) => write!(f, "no payload named {:?}", name),
+            PngmeError::InvalidAnimation(reason) => write!(f, "broken APNG animation: {}", reason),
+            PngmeError::InvalidKeyword(keyword) => write!(
+                f,
+                "invalid keyword {:?}: keywords are 1-79 printable Latin-1 characters without \
+                 leading, trailing or repeated spaces",
+                keyword
+            ),
+            PngmeError::InvalidTextChunk(reason) => write!(f, "invalid text chunk: {}", reason),
+            PngmeError::KeywordNotFound(keyword) => {
+                write!(f, "no text chunk with keyword {:?}", keyword)
+            }
+            PngmeError::UnreadableData { offset, reason } => {
+                write!(f, "unreadable data from offset {}: {}", offset, reason)
+            }
+            PngmeError::ValidationFailed { issues } => {
+                write!(f, "validation failed with {} issue(s)", issues)
+            }
+            PngmeError::BatchFailed { failed, total } => {
+                write!(f, "{} of {} file(s) failed", failed, total)
+            }
+            PngmeError::InvalidArgument(reason) => write!(f, "invalid argument: {}", reason),
+            PngmeError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for PngmeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PngmeError::InvalidUtf8(err) => Some(err),
+            PngmeError::DecompressionFailed(err) => Some(err),
+            PngmeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PngmeError {
+    fn from(err: io::Error) -> Self {
+        PngmeError::Io(err)
+    }
+}
+
+impl From<FromUtf8Error> for PngmeError {
+    fn from(err: FromUtf8Error) -> Self {
+        PngmeError::InvalidUtf8(err)
+    }
+}
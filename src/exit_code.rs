@@ -0,0 +1,117 @@
+//! Exit codes of the command line tool. Scripts branch on them, so a code never changes meaning
+//! once it is listed in [`HELP`].
+
+use pngme::PngmeError;
+
+/// Any error without a more specific code
+pub const FAILURE: u8 = 1;
+/// A chunk, named payload, text keyword or signature does not exist
+pub const NOT_FOUND: u8 = 2;
+/// The input is not a valid PNG file, or verify found problems with it
+pub const INVALID_PNG: u8 = 3;
+/// A chunk's CRC does not match its contents
+pub const CRC_FAILURE: u8 = 4;
+/// A payload did not authenticate: wrong password, bad HMAC or bad signature
+pub const AUTH_FAILURE: u8 = 5;
+/// A payload does not fit in the image
+pub const TOO_LARGE: u8 = 6;
+/// A file could not be read or written
+pub const IO_ERROR: u8 = 7;
+/// Some of the files of a --recursive run failed
+pub const BATCH_FAILED: u8 = 8;
+/// The command line arguments are invalid
+pub const USAGE: u8 = 64;
+
+/// The exit codes as listed at the end of `--help`
+pub const HELP: &str = "\
+Exit codes:
+  0   success
+  1   any other error
+  2   chunk, named payload, text keyword or signature not found
+  3   not a valid PNG file, or verify found problems
+  4   CRC mismatch
+  5   authentication failed: wrong password, bad HMAC or bad signature
+  6   payload does not fit
+  7   file could not be read or written
+  8   some files of a --recursive run failed
+  64  invalid command line";
+
+/// The exit code to report an error with
+pub fn for_error(err: &PngmeError) -> u8 {
+    match err {
+        PngmeError::ChunkNotFound(_)
+        | PngmeError::KeywordNotFound(_)
+        | PngmeError::PayloadNotFound(_)
+        | PngmeError::SignatureNotFound(_) => NOT_FOUND,
+        PngmeError::InvalidChunkType { .. }
+        | PngmeError::InvalidChunkTypeLength(_)
+        | PngmeError::InvalidChunkLength { .. }
+        | PngmeError::TruncatedChunk { .. }
+        | PngmeError::MissingSignature
+        | PngmeError::InvalidHeader(_)
+        | PngmeError::InvalidImageData(_)
+        | PngmeError::UnreadableData { .. }
+        | PngmeError::ValidationFailed { .. } => INVALID_PNG,
+        PngmeError::InvalidCrc { .. } => CRC_FAILURE,
+        PngmeError::DecryptionFailed
+        | PngmeError::PasswordRequired
+        | PngmeError::InvalidHmac
+        | PngmeError::InvalidSignature => AUTH_FAILURE,
+        PngmeError::PayloadTooLarge { .. } => TOO_LARGE,
+        PngmeError::Io(_) => IO_ERROR,
+        PngmeError::BatchFailed { .. } => BATCH_FAILED,
+        PngmeError::InvalidArgument(_) => USAGE,
+        _ => FAILURE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pngme::ChunkType;
+    use std::io;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_for_error() {
+        let cases = [
+            (PngmeError::ChunkNotFound("ruSt".to_string()), NOT_FOUND),
+            (PngmeError::SignatureNotFound("ruSt".to_string()), NOT_FOUND),
+            (PngmeError::MissingSignature, INVALID_PNG),
+            (PngmeError::TruncatedChunk { offset: 8 }, INVALID_PNG),
+            (
+                PngmeError::InvalidCrc {
+                    chunk_type: ChunkType::from_str("ruSt").unwrap(),
+                    expected: 1,
+                    actual: 2,
+                },
+                CRC_FAILURE,
+            ),
+            (PngmeError::DecryptionFailed, AUTH_FAILURE),
+            (PngmeError::InvalidHmac, AUTH_FAILURE),
+            (
+                PngmeError::PayloadTooLarge {
+                    size: 2,
+                    capacity: 1,
+                },
+                TOO_LARGE,
+            ),
+            (
+                PngmeError::Io(io::Error::from(io::ErrorKind::NotFound)),
+                IO_ERROR,
+            ),
+            (
+                PngmeError::BatchFailed {
+                    failed: 1,
+                    total: 2,
+                },
+                BATCH_FAILED,
+            ),
+            (PngmeError::InvalidArgument("bad".to_string()), USAGE),
+            (PngmeError::InvalidKey("bad".to_string()), FAILURE),
+        ];
+        for (err, code) in cases {
+            assert_eq!(for_error(&err), code, "{:?}", err);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! A C interface to the chunk handling in this crate, declared in `include/pngme.h`.
+//!
+//! Every function takes the PNG as a pointer and a length and never keeps hold of it. Results
+//! are handed back in a [`PngmeBuffer`], which the caller owns and must release with
+//! [`pngme_free_buffer`]. When a function fails, [`pngme_last_error`] describes what went wrong.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use crate::error::PngmeError;
+use crate::png::Png;
+use crate::Result;
+
+/// Outcome of a call through the C interface
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PngmeStatus {
+    /// The call succeeded
+    Ok = 0,
+    /// A pointer was null, or a chunk type was not valid UTF-8
+    InvalidArgument = 1,
+    /// The PNG has no chunk of the requested type
+    NotFound = 2,
+    /// Anything else, see [`pngme_last_error`]
+    Error = 3,
+}
+
+/// Bytes allocated by this library and owned by the caller
+#[repr(C)]
+#[derive(Debug)]
+pub struct PngmeBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl PngmeBuffer {
+    fn empty() -> PngmeBuffer {
+        PngmeBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> PngmeBuffer {
+        let bytes = Box::into_raw(bytes.into_boxed_slice());
+        PngmeBuffer {
+            data: bytes as *mut u8,
+            len: bytes.len(),
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Stores a message under `chunk_type` right before IEND, compressed and wrapped in an envelope
+/// as `pngme encode` stores it, and writes the new PNG to `out`.
+///
+/// # Safety
+///
+/// `png` and `message` must point to at least `png_len` and `message_len` readable bytes,
+/// `chunk_type` must be a NUL-terminated string and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_encode(
+    png: *const u8,
+    png_len: usize,
+    chunk_type: *const c_char,
+    message: *const u8,
+    message_len: usize,
+    out: *mut PngmeBuffer,
+) -> PngmeStatus {
+    call(out, || {
+        let mut png = Png::try_from(slice(png, png_len)?)?;
+        png.encode_payload(text(chunk_type)?, slice(message, message_len)?, true, None)?;
+        Ok(png.as_bytes())
+    })
+}
+
+/// Reads the message stored under `chunk_type` by `pngme_encode` or `pngme encode`, joining it
+/// back together if it was split across several chunks, and writes it to `out`.
+///
+/// # Safety
+///
+/// `png` must point to at least `png_len` readable bytes, `chunk_type` must be a NUL-terminated
+/// string and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_decode(
+    png: *const u8,
+    png_len: usize,
+    chunk_type: *const c_char,
+    out: *mut PngmeBuffer,
+) -> PngmeStatus {
+    call(out, || {
+        let png = Png::try_from(slice(png, png_len)?)?;
+        png.decode_payload(text(chunk_type)?, None)
+    })
+}
+
+/// Removes the first chunk of type `chunk_type` and writes the new PNG to `out`.
+///
+/// # Safety
+///
+/// `png` must point to at least `png_len` readable bytes, `chunk_type` must be a NUL-terminated
+/// string and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_remove(
+    png: *const u8,
+    png_len: usize,
+    chunk_type: *const c_char,
+    out: *mut PngmeBuffer,
+) -> PngmeStatus {
+    call(out, || {
+        let mut png = Png::try_from(slice(png, png_len)?)?;
+        png.remove_chunk(text(chunk_type)?)?;
+        Ok(png.as_bytes())
+    })
+}
+
+/// Releases a buffer returned by this library. Freeing an empty buffer does nothing.
+///
+/// # Safety
+///
+/// `buffer` must have come from this library and must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_free_buffer(buffer: PngmeBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// Describes the last error on this thread, or returns null if there was none. The string is
+/// owned by the library and stays valid until the next call on the same thread.
+#[no_mangle]
+pub extern "C" fn pngme_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Runs one call, handing its bytes to `out` or recording its error
+unsafe fn call(out: *mut PngmeBuffer, body: impl FnOnce() -> Result<Vec<u8>>) -> PngmeStatus {
+    if out.is_null() {
+        return fail(&PngmeError::InvalidArgument(
+            "the output buffer is null".to_string(),
+        ));
+    }
+    out.write(PngmeBuffer::empty());
+    match body() {
+        Ok(bytes) => {
+            LAST_ERROR.with(|last| last.replace(None));
+            out.write(PngmeBuffer::from_vec(bytes));
+            PngmeStatus::Ok
+        }
+        Err(err) => fail(&err),
+    }
+}
+
+fn fail(err: &PngmeError) -> PngmeStatus {
+    let message = CString::new(err.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| last.replace(Some(message)));
+    match err {
+        PngmeError::InvalidArgument(_) => PngmeStatus::InvalidArgument,
+        PngmeError::ChunkNotFound(_) => PngmeStatus::NotFound,
+        _ => PngmeStatus::Error,
+    }
+}
+
+unsafe fn slice<'a>(bytes: *const u8, len: usize) -> Result<&'a [u8]> {
+    match (bytes.is_null(), len) {
+        (_, 0) => Ok(&[]),
+        (true, _) => Err(PngmeError::InvalidArgument(
+            "a data pointer is null".to_string(),
+        )),
+        (false, _) => Ok(std::slice::from_raw_parts(bytes, len)),
+    }
+}
+
+unsafe fn text<'a>(string: *const c_char) -> Result<&'a str> {
+    if string.is_null() {
+        return Err(PngmeError::InvalidArgument(
+            "the chunk type is null".to_string(),
+        ));
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| PngmeError::InvalidArgument("the chunk type is not valid UTF-8".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+        .as_bytes()
+    }
+
+    unsafe fn take(buffer: PngmeBuffer) -> Vec<u8> {
+        let bytes = std::slice::from_raw_parts(buffer.data, buffer.len).to_vec();
+        pngme_free_buffer(buffer);
+        bytes
+    }
+
+    #[test]
+    pub fn test_round_trip() {
+        let png = testing_bytes();
+        let mut out = PngmeBuffer::empty();
+        unsafe {
+            let status = pngme_encode(
+                png.as_ptr(),
+                png.len(),
+                c"ruSt".as_ptr(),
+                b"hello".as_ptr(),
+                5,
+                &mut out,
+            );
+            assert_eq!(status, PngmeStatus::Ok);
+            let encoded = take(out);
+
+            let mut out = PngmeBuffer::empty();
+            let status = pngme_decode(encoded.as_ptr(), encoded.len(), c"ruSt".as_ptr(), &mut out);
+            assert_eq!(status, PngmeStatus::Ok);
+            assert_eq!(take(out), b"hello");
+            assert!(pngme_last_error().is_null());
+
+            let mut out = PngmeBuffer::empty();
+            let status = pngme_remove(encoded.as_ptr(), encoded.len(), c"ruSt".as_ptr(), &mut out);
+            assert_eq!(status, PngmeStatus::Ok);
+            assert_eq!(take(out), png);
+        }
+    }
+
+    #[test]
+    pub fn test_errors() {
+        let png = testing_bytes();
+        let mut out = PngmeBuffer::empty();
+        unsafe {
+            let status = pngme_decode(png.as_ptr(), png.len(), c"ruSt".as_ptr(), &mut out);
+            assert_eq!(status, PngmeStatus::NotFound);
+            assert!(out.data.is_null());
+            let message = CStr::from_ptr(pngme_last_error()).to_str().unwrap();
+            assert!(message.contains("ruSt"));
+
+            let status = pngme_decode(png.as_ptr(), png.len(), ptr::null(), &mut out);
+            assert_eq!(status, PngmeStatus::InvalidArgument);
+            let status = pngme_decode(png.as_ptr(), 4, c"ruSt".as_ptr(), &mut out);
+            assert_eq!(status, PngmeStatus::Error);
+            let status = pngme_decode(png.as_ptr(), png.len(), c"ruSt".as_ptr(), ptr::null_mut());
+            assert_eq!(status, PngmeStatus::InvalidArgument);
+            pngme_free_buffer(PngmeBuffer::empty());
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt::Display;
+
+use crate::chunk::Chunk;
+use crate::error::PngmeError;
+use crate::png::Png;
+use crate::Result;
+
+/// How the samples of each pixel are to be interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ColorType {
+    /// A single grayscale sample
+    Grayscale,
+    /// Red, green and blue samples
+    Rgb,
+    /// A single index into the PLTE palette
+    Indexed,
+    /// A grayscale sample followed by an alpha sample
+    GrayscaleAlpha,
+    /// Red, green, blue and alpha samples
+    Rgba,
+}
+
+impl ColorType {
+    /// Number of samples that make up one pixel
+    pub fn channels(&self) -> u8 {
+        match self {
+            ColorType::Grayscale | ColorType::Indexed => 1,
+            ColorType::GrayscaleAlpha => 2,
+            ColorType::Rgb => 3,
+            ColorType::Rgba => 4,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<ColorType> {
+        match code {
+            0 => Some(ColorType::Grayscale),
+            2 => Some(ColorType::Rgb),
+            3 => Some(ColorType::Indexed),
+            4 => Some(ColorType::GrayscaleAlpha),
+            6 => Some(ColorType::Rgba),
+            _ => None,
+        }
+    }
+
+    fn allows_bit_depth(&self, bit_depth: u8) -> bool {
+        match self {
+            ColorType::Grayscale => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+            ColorType::Indexed => matches!(bit_depth, 1 | 2 | 4 | 8),
+            _ => matches!(bit_depth, 8 | 16),
+        }
+    }
+}
+
+impl Display for ColorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ColorType::Grayscale => "grayscale",
+            ColorType::Rgb => "RGB",
+            ColorType::Indexed => "indexed",
+            ColorType::GrayscaleAlpha => "grayscale with alpha",
+            ColorType::Rgba => "RGBA",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The image metadata stored in the IHDR chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageHeader {
+    /// Image width in pixels
+    pub width: u32,
+    /// Image height in pixels
+    pub height: u32,
+    /// Number of bits per sample, or per palette index for indexed images
+    pub bit_depth: u8,
+    /// How the samples of each pixel are to be interpreted
+    pub color_type: ColorType,
+    /// Whether the image data is stored with Adam7 interlacing
+    pub interlaced: bool,
+}
+
+impl ImageHeader {
+    /// Number of data bytes in an IHDR chunk
+    pub const LENGTH: usize = 13;
+
+    /// Number of bits that make up one pixel
+    pub fn bits_per_pixel(&self) -> u32 {
+        u32::from(self.bit_depth) * u32::from(self.color_type.channels())
+    }
+}
+
+impl TryFrom<&Chunk> for ImageHeader {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        if &chunk.chunk_type().bytes() != b"IHDR" {
+            return Err(invalid(format!(
+                "{} is not an IHDR chunk",
+                chunk.chunk_type()
+            )));
+        }
+        let data = chunk.data();
+        if data.len() != Self::LENGTH {
+            return Err(invalid(format!(
+                "IHDR holds {} bytes instead of {}",
+                data.len(),
+                Self::LENGTH
+            )));
+        }
+
+        let width = u32::from_be_bytes(data[0..4].try_into().unwrap());
+        let height = u32::from_be_bytes(data[4..8].try_into().unwrap());
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return Err(invalid(format!("invalid dimensions {}x{}", width, height)));
+        }
+        let bit_depth = data[8];
+        let color_type = ColorType::from_code(data[9])
+            .ok_or_else(|| invalid(format!("unknown color type {}", data[9])))?;
+        if !color_type.allows_bit_depth(bit_depth) {
+            return Err(invalid(format!(
+                "bit depth {} is not allowed for {} images",
+                bit_depth, color_type
+            )));
+        }
+        if data[10] != 0 || data[11] != 0 {
+            return Err(invalid(format!(
+                "unknown compression method {} or filter method {}",
+                data[10], data[11]
+            )));
+        }
+        let interlaced = match data[12] {
+            0 => false,
+            1 => true,
+            method => return Err(invalid(format!("unknown interlace method {}", method))),
+        };
+
+        Ok(ImageHeader {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            interlaced,
+        })
+    }
+}
+
+impl Png {
+    /// Parses the IHDR chunk, which the PNG spec requires to come first
+    pub fn header(&self) -> Result<ImageHeader> {
+        let chunk = self
+            .chunks()
+            .first()
+            .filter(|chunk| &chunk.chunk_type().bytes() == b"IHDR")
+            .ok_or_else(|| PngmeError::ChunkNotFound("IHDR".to_string()))?;
+        ImageHeader::try_from(chunk)
+    }
+}
+
+fn invalid(reason: String) -> PngmeError {
+    PngmeError::InvalidHeader(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn ihdr(width: u32, height: u32, bit_depth: u8, color_type: u8, interlace: u8) -> Chunk {
+        let mut data = Vec::new();
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[bit_depth, color_type, 0, 0, interlace]);
+        Chunk::new(ChunkType::from_str("IHDR").unwrap(), data)
+    }
+
+    #[test]
+    pub fn test_parse_header() {
+        let png = Png::from_chunks(vec![ihdr(640, 480, 8, 6, 1)]);
+        let header = png.header().unwrap();
+
+        assert_eq!(header.width, 640);
+        assert_eq!(header.height, 480);
+        assert_eq!(header.color_type, ColorType::Rgba);
+        assert!(header.interlaced);
+        assert_eq!(header.bits_per_pixel(), 32);
+    }
+
+    #[test]
+    pub fn test_invalid_header() {
+        for chunk in [
+            ihdr(0, 480, 8, 2, 0),
+            ihdr(640, 480, 8, 5, 0),
+            ihdr(640, 480, 4, 2, 0),
+            ihdr(640, 480, 16, 3, 0),
+            ihdr(640, 480, 8, 2, 2),
+        ] {
+            assert!(matches!(
+                ImageHeader::try_from(&chunk),
+                Err(PngmeError::InvalidHeader(_))
+            ));
+        }
+    }
+
+    #[test]
+    pub fn test_missing_header() {
+        let chunk = Chunk::new(ChunkType::from_str("IDAT").unwrap(), Vec::new());
+        let png = Png::from_chunks(vec![chunk, ihdr(1, 1, 1, 0, 0)]);
+
+        assert!(matches!(png.header(), Err(PngmeError::ChunkNotFound(_))));
+    }
+}
@@ -0,0 +1,73 @@
+//! Hide secret messages inside PNG files.
+//!
+//! A PNG file is a signature followed by a series of chunks. Messages are stored in chunks of
+//! their own, using a chunk type that PNG decoders ignore.
+//!
+//! ```
+//! use std::str::FromStr;
+//!
+//! use pngme::{Chunk, ChunkType, Png};
+//!
+//! # fn main() -> pngme::Result<()> {
+//! let mut png = Png::from_chunks(Vec::new());
+//! let chunk_type = ChunkType::from_str("ruSt")?;
+//! png.append_chunk(Chunk::new(chunk_type, b"hello".to_vec()));
+//!
+//! let png = Png::try_from(png.as_bytes().as_ref())?;
+//! let message = png.chunk_by_type("ruSt").unwrap().data_as_string()?;
+//! assert_eq!(message, "hello");
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod apng;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod borrowed;
+mod chunk;
+pub mod chunk_registry;
+mod chunk_type;
+pub mod compression;
+pub mod crypto;
+pub mod diff;
+pub mod envelope;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod header;
+mod lsb;
+pub mod mac;
+pub mod manifest;
+#[cfg(feature = "mmap")]
+mod mapped;
+mod png;
+pub mod progress;
+#[cfg(feature = "python")]
+mod python;
+mod scan;
+pub mod segment;
+#[cfg(feature = "serde")]
+mod serde_impls;
+pub mod signature;
+mod stream;
+mod text;
+pub mod validate;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use apng::{Animation, Frame};
+pub use borrowed::{ChunkRef, ChunkRefs, PngRef};
+pub use chunk::Chunk;
+pub use chunk_type::{ChunkType, ChunkTypeBuilder};
+pub use error::PngmeError;
+pub use header::{ColorType, ImageHeader};
+#[cfg(feature = "mmap")]
+pub use mapped::MappedFile;
+pub use png::{InsertPosition, Png};
+pub use scan::{ChunkInfo, Scanner};
+pub use stream::{PngReader, PngWriter};
+pub use text::{TextEntry, TextKind};
+pub use validate::ValidationIssue;
+
+pub type Error = PngmeError;
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,79 @@
+//! Wires the `tracing` events of the library up to `--verbose` logs and the progress bar
+
+use std::io;
+
+use pngme::progress::PROGRESS_TARGET;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::output;
+
+/// Logs events to stderr at DEBUG level with `verbosity` 1 and TRACE level above that, and draws
+/// progress events as a progress bar if `progress` is set
+pub fn init(verbosity: u8, progress: bool) {
+    let level = match verbosity {
+        0 => None,
+        1 => Some(Level::DEBUG),
+        _ => Some(Level::TRACE),
+    };
+    let logs = level.map(|level| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(|| {
+                output::clear_progress();
+                io::stderr()
+            })
+            .with_filter(filter_fn(move |metadata| {
+                metadata.target() != PROGRESS_TARGET && *metadata.level() <= level
+            }))
+    });
+    let progress = progress.then(|| {
+        ProgressBar.with_filter(filter_fn(|metadata| metadata.target() == PROGRESS_TARGET))
+    });
+    tracing_subscriber::registry()
+        .with(logs)
+        .with(progress)
+        .init();
+}
+
+/// Draws progress events with [`output::show_progress`]
+struct ProgressBar;
+
+impl<S: Subscriber> Layer<S> for ProgressBar {
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        let mut fields = ProgressFields::default();
+        event.record(&mut fields);
+        if let (Some(operation), Some(done), Some(total)) =
+            (fields.operation, fields.done, fields.total)
+        {
+            output::show_progress(&operation, done as usize, total as usize);
+        }
+    }
+}
+
+#[derive(Default)]
+struct ProgressFields {
+    operation: Option<String>,
+    done: Option<u64>,
+    total: Option<u64>,
+}
+
+impl Visit for ProgressFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "operation" {
+            self.operation = Some(value.to_string());
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match field.name() {
+            "done" => self.done = Some(value),
+            "total" => self.total = Some(value),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
@@ -0,0 +1,381 @@
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::compression::{deflate, inflate};
+use crate::error::PngmeError;
+use crate::header::{ColorType, ImageHeader};
+use crate::png::Png;
+use crate::progress;
+use crate::Result;
+
+/// Size of the header hidden in front of the payload: the chunk type it was stored under and the
+/// payload length as a big-endian `u32`
+pub const LSB_HEADER_BYTES: usize = 8;
+
+impl Png {
+    /// Hides bytes in the least significant bit of every sample of the image, instead of in a
+    /// chunk of their own.
+    ///
+    /// The image data is decompressed and unfiltered, the payload is written into the pixels
+    /// after a small header naming the chunk type, and the rows are refiltered with their
+    /// original filter types and recompressed into a single IDAT chunk. Only non-interlaced
+    /// grayscale and truecolor images with 8 or 16 bits per sample are supported.
+    pub fn encode_lsb(&mut self, chunk_type: &str, data: &[u8]) -> Result<()> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let mut pixels = Pixels::read(self)?;
+        let capacity = pixels.capacity();
+        tracing::debug!(capacity, payload = data.len(), "hiding payload in pixels");
+        if data.len() > capacity {
+            return Err(PngmeError::PayloadTooLarge {
+                size: data.len(),
+                capacity,
+            });
+        }
+
+        let mut framed = chunk_type.bytes().to_vec();
+        framed.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        framed.extend_from_slice(data);
+        let bits = framed
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |bit| (byte >> bit) & 1));
+        for (carrier, bit) in pixels.carriers_mut().zip(bits) {
+            *carrier = (*carrier & !1) | bit;
+        }
+        pixels.write(self)
+    }
+
+    /// Recovers bytes hidden with [`Png::encode_lsb`] under the given chunk type
+    pub fn decode_lsb(&self, chunk_type: &str) -> Result<Vec<u8>> {
+        let parsed_type = ChunkType::from_str(chunk_type)?;
+        let pixels = Pixels::read(self)?;
+        let mut bytes = pixels
+            .carriers()
+            .map(|carrier| carrier & 1)
+            .collect::<Vec<_>>()
+            .chunks_exact(8)
+            .map(|bits| bits.iter().fold(0, |byte, bit| (byte << 1) | bit))
+            .collect::<Vec<u8>>();
+
+        let not_found = || PngmeError::ChunkNotFound(chunk_type.to_string());
+        if bytes.len() < LSB_HEADER_BYTES || bytes[..4] != parsed_type.bytes() {
+            return Err(not_found());
+        }
+        let length = u32::from_be_bytes(bytes[4..8].try_into().unwrap()) as usize;
+        if length > bytes.len() - LSB_HEADER_BYTES {
+            return Err(not_found());
+        }
+        bytes.truncate(LSB_HEADER_BYTES + length);
+        Ok(bytes.split_off(LSB_HEADER_BYTES))
+    }
+
+    /// Number of payload bytes that [`Png::encode_lsb`] can hide in this image
+    pub fn lsb_capacity(&self) -> Result<usize> {
+        Ok(Pixels::read(self)?.capacity())
+    }
+}
+
+/// The unfiltered scanlines of an image, along with the filter type each was stored with
+struct Pixels {
+    header: ImageHeader,
+    filters: Vec<u8>,
+    rows: Vec<Vec<u8>>,
+}
+
+impl Pixels {
+    fn read(png: &Png) -> Result<Pixels> {
+        let header = png.header()?;
+        check_supported(&header)?;
+
+        let compressed: Vec<u8> = png
+            .chunks()
+            .iter()
+            .filter(|chunk| is_idat(chunk))
+            .flat_map(|chunk| chunk.data().iter().copied())
+            .collect();
+        if compressed.is_empty() {
+            return Err(PngmeError::ChunkNotFound("IDAT".to_string()));
+        }
+        let raw = inflate(&compressed)?;
+
+        let row_bytes = row_bytes(&header);
+        let expected = header.height as usize * (row_bytes + 1);
+        if raw.len() < expected {
+            return Err(PngmeError::InvalidImageData(format!(
+                "expected {} bytes of scanlines but IDAT holds {}",
+                expected,
+                raw.len()
+            )));
+        }
+
+        tracing::debug!(
+            width = header.width,
+            height = header.height,
+            compressed = compressed.len(),
+            raw = raw.len(),
+            "decompressed image data"
+        );
+
+        let pixel_bytes = pixel_bytes(&header);
+        let height = header.height as usize;
+        let mut filters = Vec::new();
+        let mut rows: Vec<Vec<u8>> = Vec::new();
+        for line in raw[..expected].chunks_exact(row_bytes + 1) {
+            let mut row = line[1..].to_vec();
+            let prior = rows.last().map(Vec::as_slice);
+            unfilter(line[0], &mut row, prior, pixel_bytes)?;
+            filters.push(line[0]);
+            rows.push(row);
+            progress::report("unfiltering rows", rows.len(), height);
+        }
+        Ok(Pixels {
+            header,
+            filters,
+            rows,
+        })
+    }
+
+    /// Refilters and recompresses the rows, replacing every IDAT chunk with a single one
+    fn write(&self, png: &mut Png) -> Result<()> {
+        let pixel_bytes = pixel_bytes(&self.header);
+        let mut raw = Vec::new();
+        for (index, row) in self.rows.iter().enumerate() {
+            let prior = index
+                .checked_sub(1)
+                .map(|prior| self.rows[prior].as_slice());
+            raw.push(self.filters[index]);
+            raw.extend(filter(self.filters[index], row, prior, pixel_bytes));
+            progress::report("filtering rows", index + 1, self.rows.len());
+        }
+
+        let index = png
+            .chunks()
+            .iter()
+            .position(is_idat)
+            .ok_or_else(|| PngmeError::ChunkNotFound("IDAT".to_string()))?;
+        png.remove_all_chunks("IDAT")?;
+        let chunk_type = ChunkType::from_str("IDAT")?;
+        png.insert_chunk_at(index, Chunk::new(chunk_type, deflate(&raw)))
+    }
+
+    /// Number of payload bytes that fit in the carrier bits, after the header
+    fn capacity(&self) -> usize {
+        (self.carriers().count() / 8).saturating_sub(LSB_HEADER_BYTES)
+    }
+
+    /// The low byte of every sample, whose lowest bit carries one bit of the payload
+    fn carriers(&self) -> impl Iterator<Item = u8> + '_ {
+        let step = self.sample_bytes();
+        self.rows
+            .iter()
+            .flatten()
+            .skip(step - 1)
+            .step_by(step)
+            .copied()
+    }
+
+    fn carriers_mut(&mut self) -> impl Iterator<Item = &mut u8> + '_ {
+        let step = self.sample_bytes();
+        self.rows.iter_mut().flatten().skip(step - 1).step_by(step)
+    }
+
+    fn sample_bytes(&self) -> usize {
+        usize::from(self.header.bit_depth / 8)
+    }
+}
+
+fn check_supported(header: &ImageHeader) -> Result<()> {
+    let reason = if header.interlaced {
+        "interlaced images are not supported"
+    } else if header.color_type == ColorType::Indexed {
+        "palette images are not supported, since changing an index can change the color entirely"
+    } else if header.bit_depth < 8 {
+        "bit depths below 8 are not supported"
+    } else {
+        return Ok(());
+    };
+    Err(PngmeError::UnsupportedImage(reason.to_string()))
+}
+
+fn is_idat(chunk: &Chunk) -> bool {
+    &chunk.chunk_type().bytes() == b"IDAT"
+}
+
+/// Number of bytes in one scanline, not counting the filter type byte
+fn row_bytes(header: &ImageHeader) -> usize {
+    (header.width as usize * header.bits_per_pixel() as usize).div_ceil(8)
+}
+
+/// Number of bytes in one pixel, which is the distance filters look back
+fn pixel_bytes(header: &ImageHeader) -> usize {
+    (header.bits_per_pixel() as usize).div_ceil(8)
+}
+
+/// Reverses the filter applied to a scanline, in place
+fn unfilter(
+    filter_type: u8,
+    row: &mut [u8],
+    prior: Option<&[u8]>,
+    pixel_bytes: usize,
+) -> Result<()> {
+    for x in 0..row.len() {
+        let (left, up, up_left) = neighbours(row, prior, x, pixel_bytes);
+        let predicted = predict(filter_type, left, up, up_left).ok_or_else(|| {
+            PngmeError::InvalidImageData(format!("unknown filter type {}", filter_type))
+        })?;
+        row[x] = row[x].wrapping_add(predicted);
+    }
+    Ok(())
+}
+
+/// Applies a filter to an unfiltered scanline
+fn filter(filter_type: u8, row: &[u8], prior: Option<&[u8]>, pixel_bytes: usize) -> Vec<u8> {
+    (0..row.len())
+        .map(|x| {
+            let (left, up, up_left) = neighbours(row, prior, x, pixel_bytes);
+            let predicted = predict(filter_type, left, up, up_left)
+                .expect("filter types were checked when the rows were read");
+            row[x].wrapping_sub(predicted)
+        })
+        .collect()
+}
+
+/// The unfiltered bytes to the left of, above, and above-left of position `x`
+fn neighbours(row: &[u8], prior: Option<&[u8]>, x: usize, pixel_bytes: usize) -> (u8, u8, u8) {
+    let left = x.checked_sub(pixel_bytes).map_or(0, |left| row[left]);
+    let up = prior.map_or(0, |prior| prior[x]);
+    let up_left = match (prior, x.checked_sub(pixel_bytes)) {
+        (Some(prior), Some(left)) => prior[left],
+        _ => 0,
+    };
+    (left, up, up_left)
+}
+
+/// The value a filter type predicts for a byte from its neighbours
+fn predict(filter_type: u8, left: u8, up: u8, up_left: u8) -> Option<u8> {
+    match filter_type {
+        0 => Some(0),
+        1 => Some(left),
+        2 => Some(up),
+        3 => Some(((u16::from(left) + u16::from(up)) / 2) as u8),
+        4 => Some(paeth(left, up, up_left)),
+        _ => None,
+    }
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+    let distance = |value: u8| (estimate - i16::from(value)).abs();
+    if distance(left) <= distance(up) && distance(left) <= distance(up_left) {
+        left
+    } else if distance(up) <= distance(up_left) {
+        up
+    } else {
+        up_left
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+    }
+
+    /// A 16x8 image whose rows cycle through the five filter types
+    fn testing_png(bit_depth: u8, color_type: u8, interlace: u8) -> Png {
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&16u32.to_be_bytes());
+        ihdr.extend_from_slice(&8u32.to_be_bytes());
+        ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, interlace]);
+        let header = ImageHeader::try_from(&chunk("IHDR", ihdr.clone())).unwrap();
+
+        let rows: Vec<Vec<u8>> = (0..8)
+            .map(|y| {
+                (0..row_bytes(&header))
+                    .map(|x| (x * 37 + y * 91) as u8)
+                    .collect()
+            })
+            .collect();
+        let mut raw = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            let prior = y.checked_sub(1).map(|prior| rows[prior].as_slice());
+            let filter_type = (y % 5) as u8;
+            raw.push(filter_type);
+            raw.extend(filter(filter_type, row, prior, pixel_bytes(&header)));
+        }
+
+        Png::from_chunks(vec![
+            chunk("IHDR", ihdr),
+            chunk("IDAT", deflate(&raw)),
+            chunk("IEND", Vec::new()),
+        ])
+    }
+
+    #[test]
+    pub fn test_round_trip() {
+        for (bit_depth, color_type) in [(8, 2), (8, 6), (16, 0), (16, 4)] {
+            let mut png = testing_png(bit_depth, color_type, 0);
+            let original = Pixels::read(&png).unwrap();
+            png.encode_lsb("ruSt", b"Hi!").unwrap();
+
+            let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+            assert_eq!(png.decode_lsb("ruSt").unwrap(), b"Hi!");
+            assert_eq!(png.chunks().len(), 3);
+
+            let changed = Pixels::read(&png).unwrap();
+            let differences = original
+                .rows
+                .iter()
+                .flatten()
+                .zip(changed.rows.iter().flatten())
+                .map(|(before, after)| before ^ after);
+            assert!(differences.clone().all(|difference| difference <= 1));
+            assert!(differences.clone().any(|difference| difference == 1));
+        }
+    }
+
+    #[test]
+    pub fn test_capacity() {
+        let mut png = testing_png(8, 2, 0);
+        let capacity = png.lsb_capacity().unwrap();
+
+        assert_eq!(capacity, 16 * 8 * 3 / 8 - LSB_HEADER_BYTES);
+        assert!(matches!(
+            png.encode_lsb("ruSt", &vec![0; capacity + 1]),
+            Err(PngmeError::PayloadTooLarge { .. })
+        ));
+        png.encode_lsb("ruSt", &vec![7; capacity]).unwrap();
+        assert_eq!(png.decode_lsb("ruSt").unwrap(), vec![7; capacity]);
+    }
+
+    #[test]
+    pub fn test_missing_payload() {
+        let mut png = testing_png(8, 2, 0);
+        assert!(matches!(
+            png.decode_lsb("ruSt"),
+            Err(PngmeError::ChunkNotFound(_))
+        ));
+
+        png.encode_lsb("ruSt", b"Hi!").unwrap();
+        assert!(matches!(
+            png.decode_lsb("teSt"),
+            Err(PngmeError::ChunkNotFound(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_unsupported_images() {
+        for png in [
+            testing_png(8, 2, 1),
+            testing_png(8, 3, 0),
+            testing_png(4, 0, 0),
+        ] {
+            assert!(matches!(
+                png.lsb_capacity(),
+                Err(PngmeError::UnsupportedImage(_))
+            ));
+        }
+    }
+}
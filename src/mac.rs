@@ -0,0 +1,97 @@
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::PngmeError;
+use crate::Result;
+
+/// Size of the HMAC-SHA256 tag that [`append_hmac`] adds to a payload
+pub const HMAC_BYTES: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Appends an HMAC-SHA256 tag keyed by a passphrase to a payload.
+///
+/// The tag covers the chunk type as well as the data, like a signature does, so a payload cannot
+/// be moved to another chunk type without the check failing. Unlike encryption this leaves the
+/// payload readable and only detects tampering.
+pub fn append_hmac(chunk_type: &str, data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let tag = mac(&ChunkType::from_str(chunk_type)?, data, passphrase).finalize();
+    Ok(data
+        .iter()
+        .chain(tag.into_bytes().iter())
+        .copied()
+        .collect())
+}
+
+/// Checks the tag at the end of a payload produced by [`append_hmac`] and returns the payload
+/// without it. Fails with [`PngmeError::InvalidHmac`] if the payload was changed, the passphrase
+/// is wrong or there is no tag at all.
+pub fn strip_hmac<'a>(chunk_type: &str, data: &'a [u8], passphrase: &str) -> Result<&'a [u8]> {
+    let chunk_type = ChunkType::from_str(chunk_type)?;
+    if data.len() < HMAC_BYTES {
+        return Err(PngmeError::InvalidHmac);
+    }
+    let (payload, tag) = data.split_at(data.len() - HMAC_BYTES);
+    mac(&chunk_type, payload, passphrase)
+        .verify_slice(tag)
+        .map_err(|_| PngmeError::InvalidHmac)?;
+    Ok(payload)
+}
+
+impl Chunk {
+    /// Checks the HMAC-SHA256 tag at the end of this chunk's data, see [`append_hmac`], and
+    /// returns the data before it.
+    ///
+    /// A payload split across several chunks carries a single tag in its last part, so check the
+    /// joined payload with [`strip_hmac`] instead.
+    pub fn verify_hmac(&self, passphrase: &str) -> Result<&[u8]> {
+        strip_hmac(&self.chunk_type().to_string(), self.data(), passphrase)
+    }
+}
+
+fn mac(chunk_type: &ChunkType, data: &[u8], passphrase: &str) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(passphrase.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(&chunk_type.bytes());
+    mac.update(data);
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_round_trip() {
+        let tagged = append_hmac("ruSt", b"plain message", "hunter2").unwrap();
+
+        assert_eq!(tagged.len(), b"plain message".len() + HMAC_BYTES);
+        assert!(tagged.starts_with(b"plain message"));
+        assert_eq!(
+            strip_hmac("ruSt", &tagged, "hunter2").unwrap(),
+            b"plain message"
+        );
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), tagged);
+        assert_eq!(chunk.verify_hmac("hunter2").unwrap(), b"plain message");
+    }
+
+    #[test]
+    pub fn test_tampering_detected() {
+        let mut tagged = append_hmac("ruSt", b"plain message", "hunter2").unwrap();
+
+        assert!(matches!(
+            strip_hmac("ruSt", &tagged, "wrong"),
+            Err(PngmeError::InvalidHmac)
+        ));
+        assert!(strip_hmac("ruSx", &tagged, "hunter2").is_err());
+        assert!(strip_hmac("ruSt", b"short", "hunter2").is_err());
+        tagged[0] ^= 1;
+        assert!(Chunk::new(ChunkType::from_str("ruSt").unwrap(), tagged)
+            .verify_hmac("hunter2")
+            .is_err());
+    }
+}
@@ -1,13 +1,78 @@
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+
+use args::{Cli, PngMeArgs, TextCommand};
+use config::Config;
+
+#[macro_use]
+mod output;
+
 mod args;
-mod chunk;
-mod chunk_type;
+mod batch;
 mod commands;
-mod png;
+mod config;
+mod encoding;
+mod exit_code;
+mod logging;
+mod secrets;
 
-pub type Error = Box<dyn std::error::Error>;
-pub type Result<T> = std::result::Result<T, Error>;
-
-fn main() -> Result<()> {
-    todo!()
+fn main() -> ExitCode {
+    let config = match Config::load(Cli::config_path().as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("pngme: {}", err);
+            return ExitCode::from(exit_code::for_error(&err));
+        }
+    };
+    let cli = match Cli::try_parse_with(std::env::args_os(), &config) {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = err.print();
+            return match err.use_stderr() {
+                true => ExitCode::from(exit_code::USAGE),
+                false => ExitCode::SUCCESS,
+            };
+        }
+    };
+    output::set_quiet(cli.quiet);
+    commands::set_lenient(cli.lenient);
+    logging::init(cli.verbose, !cli.quiet && io::stderr().is_terminal());
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        // The reader of stdout went away, as with `pngme decode ... | head`, which is not a failure
+        Err(pngme::PngmeError::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe => {
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            output::clear_progress();
+            eprintln!("pngme: {}", err);
+            ExitCode::from(exit_code::for_error(&err))
+        }
+    }
 }
 
+fn run(cli: Cli) -> pngme::Result<()> {
+    match cli.command {
+        PngMeArgs::Encode(args) => commands::encode(args),
+        PngMeArgs::Decode(args) => commands::decode(args, cli.format),
+        PngMeArgs::Remove(args) => commands::remove(args),
+        PngMeArgs::Insert(args) => commands::insert(args),
+        PngMeArgs::Extract(args) => commands::extract(args),
+        PngMeArgs::Inspect(args) => commands::inspect(args, cli.format),
+        PngMeArgs::LsPayloads(args) => commands::ls_payloads(args, cli.format),
+        PngMeArgs::Strip(args) => commands::strip(args),
+        PngMeArgs::Print(args) => commands::print_chunks(args, cli.format),
+        PngMeArgs::Info(args) => commands::info(args, cli.format),
+        PngMeArgs::Capacity(args) => commands::capacity(args, cli.format),
+        PngMeArgs::Scan(args) => commands::scan(args, cli.format),
+        PngMeArgs::Diff(args) => commands::diff(args, cli.format),
+        PngMeArgs::Verify(args) => commands::verify(args, cli.format),
+        PngMeArgs::Repair(args) => commands::repair(args),
+        PngMeArgs::Completions(args) => commands::completions(args),
+        PngMeArgs::Manpage(args) => commands::manpage(args),
+        PngMeArgs::Text(args) => match args.command {
+            TextCommand::Get(args) => commands::text_get(args),
+            TextCommand::Set(args) => commands::text_set(args),
+        },
+    }
+}
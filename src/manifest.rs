@@ -0,0 +1,294 @@
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::PngmeError;
+use crate::png::{InsertPosition, Png};
+use crate::segment;
+use crate::Result;
+
+/// Chunk type that the manifest of named payloads is stored under: ancillary, private and safe to
+/// copy
+pub const MANIFEST_CHUNK_TYPE: &str = "mnFt";
+
+/// Payload names are limited to this many bytes
+pub const MAX_NAME_BYTES: usize = 255;
+
+/// One named payload: the chunk type it is stored under and the CRCs of its chunks, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub chunk_type: ChunkType,
+    /// CRC of every chunk holding the payload, so the chunks are found wherever they have moved
+    pub crcs: Vec<u32>,
+}
+
+/// The names of the payloads stored in a PNG.
+///
+/// The manifest is stored in a single [`MANIFEST_CHUNK_TYPE`] chunk as UTF-8 text with one line
+/// per payload: the name, the chunk type and the comma-separated hex CRCs of the payload's chunks,
+/// separated by tabs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Parses the data of a manifest chunk
+    pub fn from_bytes(bytes: &[u8]) -> Result<Manifest> {
+        let invalid = |reason: String| PngmeError::InvalidManifest(reason);
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| invalid("manifest is not valid UTF-8".to_string()))?;
+        let entries = text
+            .lines()
+            .map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                let [name, chunk_type, crcs] = fields[..] else {
+                    return Err(invalid(format!("malformed line {:?}", line)));
+                };
+                let crcs = crcs
+                    .split(',')
+                    .map(|crc| u32::from_str_radix(crc, 16))
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|_| invalid(format!("malformed CRCs for payload {:?}", name)))?;
+                Ok(ManifestEntry {
+                    name: name.to_string(),
+                    chunk_type: ChunkType::from_str(chunk_type)?,
+                    crcs,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Manifest { entries })
+    }
+
+    /// Serializes the manifest as the data of a manifest chunk
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let crcs: Vec<String> = entry
+                    .crcs
+                    .iter()
+                    .map(|crc| format!("{:08x}", crc))
+                    .collect();
+                format!("{}\t{}\t{}\n", entry.name, entry.chunk_type, crcs.join(","))
+            })
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    /// Every named payload, in the order they were first stored
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+
+    /// The payload stored under a name, if there is one
+    pub fn get(&self, name: &str) -> Option<&ManifestEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Adds an entry, replacing and returning any earlier entry with the same name
+    pub fn insert(&mut self, entry: ManifestEntry) -> Option<ManifestEntry> {
+        match self.entries.iter_mut().find(|old| old.name == entry.name) {
+            Some(old) => Some(std::mem::replace(old, entry)),
+            None => {
+                self.entries.push(entry);
+                None
+            }
+        }
+    }
+}
+
+impl Png {
+    /// The manifest of named payloads, empty if nothing was stored by name
+    pub fn manifest(&self) -> Result<Manifest> {
+        match self.chunk_by_type(MANIFEST_CHUNK_TYPE) {
+            Some(chunk) => Manifest::from_bytes(chunk.data()),
+            None => Ok(Manifest::default()),
+        }
+    }
+
+    /// Stores a payload under a chunk type like [`Png::encode_segmented_at`] and records it in the
+    /// manifest under `name`. A payload stored earlier under the same name is removed first.
+    pub fn encode_named(
+        &mut self,
+        name: &str,
+        chunk_type: &str,
+        data: &[u8],
+        max_chunk_size: usize,
+        position: InsertPosition,
+    ) -> Result<()> {
+        check_name(name)?;
+        let parsed_type = ChunkType::from_str(chunk_type)?;
+        let mut manifest = self.manifest()?;
+        let crcs = segment::split(data, max_chunk_size)?
+            .into_iter()
+            .map(|part| Chunk::new(parsed_type.clone(), part).crc())
+            .collect();
+        let old = manifest.get(name).cloned();
+        let old_payload = old.as_ref().and_then(|old| self.named_payload(old));
+        if let Some(old) = &old {
+            self.remove_payload_chunks(old);
+        }
+        self.encode_segmented_at(chunk_type, data, max_chunk_size, position)?;
+
+        manifest.insert(ManifestEntry {
+            name: name.to_string(),
+            chunk_type: parsed_type,
+            crcs,
+        });
+        // The old payload's signature goes with it, unless another payload holds the same bytes
+        if let (Some(old), Some(old_payload)) = (old, old_payload) {
+            let still_stored = manifest.entries().iter().any(|entry| {
+                entry.chunk_type == old.chunk_type
+                    && self.named_payload(entry).as_ref() == Some(&old_payload)
+            });
+            if !still_stored {
+                self.remove_payload_signatures(&old.chunk_type, &old_payload);
+            }
+        }
+        self.remove_all_chunks(MANIFEST_CHUNK_TYPE).ok();
+        self.insert_chunk(
+            Chunk::new(
+                ChunkType::from_str(MANIFEST_CHUNK_TYPE)?,
+                manifest.to_bytes(),
+            ),
+            InsertPosition::BeforeIend,
+        )
+    }
+
+    /// Reassembles the payload stored under a name. Fails with [`PngmeError::PayloadNotFound`] if
+    /// no payload has that name, or [`PngmeError::InvalidManifest`] if one of its chunks is gone.
+    pub fn decode_named(&self, name: &str) -> Result<Vec<u8>> {
+        let manifest = self.manifest()?;
+        let entry = manifest
+            .get(name)
+            .ok_or_else(|| PngmeError::PayloadNotFound(name.to_string()))?;
+        segment::join(self.payload_chunks(entry)?)
+    }
+
+    /// The data of every chunk of a named payload, in order
+    pub fn payload_chunks(&self, entry: &ManifestEntry) -> Result<Vec<&[u8]>> {
+        entry
+            .crcs
+            .iter()
+            .map(|crc| {
+                self.chunks()
+                    .iter()
+                    .find(|chunk| *chunk.chunk_type() == entry.chunk_type && chunk.crc() == *crc)
+                    .map(Chunk::data)
+                    .ok_or_else(|| {
+                        PngmeError::InvalidManifest(format!(
+                            "payload {:?} is missing its {} chunk with CRC {:08x}",
+                            entry.name, entry.chunk_type, crc
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// The payload of a manifest entry, or None if it cannot be reassembled
+    fn named_payload(&self, entry: &ManifestEntry) -> Option<Vec<u8>> {
+        segment::join(self.payload_chunks(entry).ok()?).ok()
+    }
+
+    /// Removes one chunk for every CRC of the entry, so an identical payload stored under another
+    /// name keeps its own chunks
+    fn remove_payload_chunks(&mut self, entry: &ManifestEntry) {
+        let mut crcs = entry.crcs.clone();
+        self.retain_chunks(|chunk| {
+            if *chunk.chunk_type() != entry.chunk_type {
+                return true;
+            }
+            match crcs.iter().position(|crc| *crc == chunk.crc()) {
+                Some(index) => {
+                    crcs.swap_remove(index);
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > MAX_NAME_BYTES || name.chars().any(char::is_control) {
+        return Err(PngmeError::InvalidManifest(format!(
+            "invalid payload name {:?}: names are 1-{} bytes without control characters",
+            name, MAX_NAME_BYTES
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+    }
+
+    #[test]
+    pub fn test_named_payloads() {
+        let mut png = testing_png();
+        png.encode_named(
+            "config",
+            "ruSt",
+            b"debug = true",
+            1024,
+            InsertPosition::BeforeIend,
+        )
+        .unwrap();
+        png.encode_named("notes", "ruSt", &[7; 100], 40, InsertPosition::AfterIhdr)
+            .unwrap();
+
+        assert_eq!(png.decode_named("config").unwrap(), b"debug = true");
+        assert_eq!(png.decode_named("notes").unwrap(), vec![7; 100]);
+        let manifest = png.manifest().unwrap();
+        let names: Vec<&str> = manifest.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["config", "notes"]);
+        assert_eq!(manifest.get("notes").unwrap().crcs.len(), 4);
+        assert!(matches!(
+            png.decode_named("missing"),
+            Err(PngmeError::PayloadNotFound(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_replace_named_payload() {
+        let mut png = testing_png();
+        png.encode_named("a", "ruSt", b"same", 1024, InsertPosition::BeforeIend)
+            .unwrap();
+        png.encode_named("b", "ruSt", b"same", 1024, InsertPosition::BeforeIend)
+            .unwrap();
+        png.encode_named("a", "ruSt", b"new", 1024, InsertPosition::BeforeIend)
+            .unwrap();
+
+        assert_eq!(png.decode_named("a").unwrap(), b"new");
+        assert_eq!(png.decode_named("b").unwrap(), b"same");
+        assert_eq!(png.manifest().unwrap().entries().len(), 2);
+        assert_eq!(png.chunks().len(), 5);
+    }
+
+    #[test]
+    pub fn test_manifest_round_trip() {
+        let mut manifest = Manifest::default();
+        manifest.insert(ManifestEntry {
+            name: "config file".to_string(),
+            chunk_type: ChunkType::from_str("ruSt").unwrap(),
+            crcs: vec![0x1a2b3c4d, 7],
+        });
+
+        let bytes = manifest.to_bytes();
+        assert_eq!(bytes, b"config file\truSt\t1a2b3c4d,00000007\n");
+        assert_eq!(Manifest::from_bytes(&bytes).unwrap(), manifest);
+        assert!(Manifest::from_bytes(b"name\truSt\n").is_err());
+        assert!(testing_png()
+            .encode_named("bad\nname", "ruSt", b"", 1024, InsertPosition::BeforeIend)
+            .is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::borrowed::PngRef;
+use crate::Result;
+
+/// A file mapped into memory, so huge PNGs can be walked with [`PngRef`] without reading them
+/// into RAM first. Pages are only loaded as chunks are touched.
+///
+/// The file must not be changed by another process while it is mapped, or the bytes seen
+/// through the map may change underneath the parser.
+#[derive(Debug)]
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Maps a file into memory, read-only
+    pub fn open(path: impl AsRef<Path>) -> Result<MappedFile> {
+        let file = File::open(path)?;
+        // Safety: the map is read-only and the docs above require the file not to change while
+        // it is mapped, which is the same contract as reading the file in one go.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(MappedFile { map })
+    }
+
+    /// The bytes of the file
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Borrows the file as a PNG, checking the signature
+    pub fn png_ref(&self) -> Result<PngRef<'_>> {
+        PngRef::new(&self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::png::Png;
+    use crate::PngmeError;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_mapped_file() {
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ]);
+        let path = std::env::temp_dir().join(format!("pngme-mapped-{}.png", std::process::id()));
+        std::fs::write(&path, png.as_bytes()).unwrap();
+
+        let mapped = MappedFile::open(&path).unwrap();
+        assert_eq!(mapped.as_bytes(), png.as_bytes());
+        assert_eq!(mapped.png_ref().unwrap().chunks().count(), 2);
+        assert_eq!(Png::from_file(&path).unwrap().as_bytes(), png.as_bytes());
+
+        std::fs::write(&path, b"").unwrap();
+        let mapped = MappedFile::open(&path).unwrap();
+        assert!(matches!(
+            mapped.png_ref(),
+            Err(PngmeError::MissingSignature)
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
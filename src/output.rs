@@ -0,0 +1,105 @@
+//! Normal output of the command line tool, which `--quiet` turns off, and the progress bar drawn
+//! on a terminal. Errors are always printed.
+
+use std::io::{self, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::exit_code;
+
+/// Width of the progress bar, in characters
+const BAR_WIDTH: usize = 24;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// The operation and percentage the progress bar shows, if it is drawn
+static PROGRESS: Mutex<Option<(String, usize)>> = Mutex::new(None);
+
+/// Turns normal output off or on for the rest of the run
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Whether normal output is turned off
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Draws a progress bar on the current line of stderr, or erases it once the operation is done.
+/// The bar is only redrawn when the percentage changes.
+pub fn show_progress(operation: &str, done: usize, total: usize) {
+    let mut shown = PROGRESS.lock().unwrap();
+    if done >= total {
+        if shown.take().is_some() {
+            eprint!("\r\x1b[2K");
+        }
+        return;
+    }
+    let percent = done * 100 / total;
+    if shown
+        .as_ref()
+        .is_some_and(|(shown, shown_percent)| shown == operation && *shown_percent == percent)
+    {
+        return;
+    }
+    let filled = percent * BAR_WIDTH / 100;
+    eprint!(
+        "\r\x1b[2K{} [{}{}] {:>3}%",
+        operation,
+        "#".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        percent
+    );
+    *shown = Some((operation.to_string(), percent));
+}
+
+/// Erases the progress bar, so a line can be printed in its place
+pub fn clear_progress() {
+    if PROGRESS.lock().unwrap().take().is_some() {
+        eprint!("\r\x1b[2K");
+    }
+}
+
+/// Writes text to stdout. `print!` panics once the reader has gone away, as it does with
+/// `pngme decode image.png ruSt | head -c 20`, so a broken pipe ends the run successfully instead,
+/// and any other failure ends it with [`exit_code::IO_ERROR`].
+pub fn print(text: &str) {
+    let mut stdout = io::stdout().lock();
+    let written = stdout
+        .write_all(text.as_bytes())
+        .and_then(|()| stdout.flush());
+    match written {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        Err(err) => {
+            eprintln!("pngme: {}", err);
+            process::exit(exit_code::IO_ERROR.into());
+        }
+    }
+}
+
+/// Like `println!`, but prints nothing with `--quiet`. The arguments are evaluated either way, so
+/// any `?` in them still fails the command.
+macro_rules! say {
+    () => {
+        say!("")
+    };
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        if !$crate::output::is_quiet() {
+            $crate::output::clear_progress();
+            $crate::output::print(&format!("{}\n", line));
+        }
+    }};
+}
+
+/// Like `eprintln!` for status messages, which `--quiet` turns off too
+macro_rules! say_status {
+    ($($arg:tt)*) => {{
+        if !$crate::output::is_quiet() {
+            $crate::output::clear_progress();
+            eprintln!($($arg)*);
+        }
+    }};
+}
@@ -0,0 +1,801 @@
+use std::fmt::Display;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::crypto;
+use crate::envelope::{self, Envelope};
+use crate::error::PngmeError;
+use crate::segment;
+use crate::text::TextEntry;
+use crate::Result;
+
+/// Where a new chunk is placed among the chunks of a PNG
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InsertPosition {
+    /// Right before IEND, or at the end if there is none. Many decoders ignore anything after
+    /// IEND, so this is the safest place for a chunk that has to survive
+    #[default]
+    BeforeIend,
+    /// Right after IHDR, or at the start if there is none
+    AfterIhdr,
+    /// At this index in the chunk list, shifting later chunks along
+    AtIndex(usize),
+}
+
+/// A PNG file: the 8-byte signature followed by a series of chunks
+#[derive(Debug, Clone)]
+pub struct Png {
+    chunks: Vec<Chunk>,
+}
+
+impl Png {
+    /// The first eight bytes of every PNG file
+    pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+    /// Creates a PNG from a list of chunks
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Png {
+        Png { chunks }
+    }
+
+    /// Reads and parses a PNG file. With the `mmap` feature the file is memory-mapped instead of
+    /// being read into a buffer first, so only the parsed chunks take up memory.
+    #[cfg(feature = "fs")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Png> {
+        #[cfg(feature = "mmap")]
+        return crate::mapped::MappedFile::open(path)?.png_ref()?.to_png();
+        #[cfg(not(feature = "mmap"))]
+        Png::try_from(std::fs::read(path)?.as_ref())
+    }
+
+    /// Appends a chunk to the end of the PNG
+    pub fn append_chunk(&mut self, chunk: Chunk) {
+        self.chunks.push(chunk);
+    }
+
+    /// Inserts a chunk at the given index, shifting later chunks along
+    pub fn insert_chunk_at(&mut self, index: usize, chunk: Chunk) -> Result<()> {
+        if index > self.chunks.len() {
+            return Err(PngmeError::IndexOutOfRange {
+                index,
+                len: self.chunks.len(),
+            });
+        }
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    /// Inserts a chunk at the given position
+    pub fn insert_chunk(&mut self, chunk: Chunk, position: InsertPosition) -> Result<()> {
+        self.insert_chunk_at(self.insertion_index(position), chunk)
+    }
+
+    /// Removes the first chunk matching the given chunk type and returns it
+    pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let index = self
+            .chunks
+            .iter()
+            .position(|chunk| *chunk.chunk_type() == chunk_type)
+            .ok_or_else(|| PngmeError::ChunkNotFound(chunk_type.to_string()))?;
+        Ok(self.chunks.remove(index))
+    }
+
+    /// Removes every chunk matching the given chunk type and returns them, in file order
+    pub fn remove_all_chunks(&mut self, chunk_type: &str) -> Result<Vec<Chunk>> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let (removed, kept) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| *chunk.chunk_type() == chunk_type);
+        self.chunks = kept;
+        if removed.is_empty() {
+            return Err(PngmeError::ChunkNotFound(chunk_type.to_string()));
+        }
+        Ok(removed)
+    }
+
+    /// Keeps only the chunks the predicate returns true for and returns the others, in file order
+    pub fn retain_chunks(&mut self, mut keep: impl FnMut(&Chunk) -> bool) -> Vec<Chunk> {
+        let (kept, removed) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| keep(chunk));
+        self.chunks = kept;
+        removed
+    }
+
+    /// Removes the chunk at the given index and returns it
+    pub fn remove_chunk_at(&mut self, index: usize) -> Result<Chunk> {
+        if index >= self.chunks.len() {
+            return Err(PngmeError::IndexOutOfRange {
+                index,
+                len: self.chunks.len(),
+            });
+        }
+        Ok(self.chunks.remove(index))
+    }
+
+    /// The chunks of this PNG, in file order. Call `iter()` on the slice, or iterate over `&png`,
+    /// to walk them without copying any chunk data
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    pub(crate) fn chunks_mut(&mut self) -> &mut [Chunk] {
+        &mut self.chunks
+    }
+
+    /// Finds the first chunk matching the given chunk type
+    pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
+        self.chunks_by_type(chunk_type).next()
+    }
+
+    /// Every chunk of the given type, in file order. An invalid chunk type matches nothing
+    pub fn chunks_by_type<'a>(&'a self, chunk_type: &str) -> impl Iterator<Item = &'a Chunk> {
+        let chunk_type = ChunkType::from_str(chunk_type).ok();
+        self.chunks
+            .iter()
+            .filter(move |chunk| chunk_type.as_ref() == Some(chunk.chunk_type()))
+    }
+
+    /// The index of the first chunk of the given type
+    pub fn position_of(&self, chunk_type: &str) -> Option<usize> {
+        let chunk_type = ChunkType::from_str(chunk_type).ok()?;
+        self.chunks
+            .iter()
+            .position(|chunk| *chunk.chunk_type() == chunk_type)
+    }
+
+    /// Finds the first chunk the predicate returns true for. `chunks().iter().filter` finds every
+    /// match, e.g. all the private chunks whose type starts with a prefix
+    pub fn find(&self, mut predicate: impl FnMut(&Chunk) -> bool) -> Option<&Chunk> {
+        self.chunks.iter().find(|chunk| predicate(chunk))
+    }
+
+    /// The raw data of the chunk of the given type at `index` among the chunks of that type,
+    /// counting from 0
+    pub fn chunk_data(&self, chunk_type: &str, index: usize) -> Result<&[u8]> {
+        let parsed_type = ChunkType::from_str(chunk_type)?;
+        self.chunks
+            .iter()
+            .filter(|chunk| *chunk.chunk_type() == parsed_type)
+            .nth(index)
+            .map(Chunk::data)
+            .ok_or_else(|| PngmeError::ChunkNotFound(format!("{} #{}", chunk_type, index)))
+    }
+
+    /// Stores arbitrary bytes as a new chunk of the given type, right before IEND
+    pub fn encode_bytes(&mut self, chunk_type: &str, data: &[u8]) -> Result<()> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        self.insert_chunk(
+            Chunk::new(chunk_type, data.to_vec()),
+            InsertPosition::BeforeIend,
+        )
+    }
+
+    /// Stores arbitrary bytes under the given chunk type right before IEND, split across as many
+    /// chunks as needed so that none holds more than `max_chunk_size` bytes
+    pub fn encode_segmented(
+        &mut self,
+        chunk_type: &str,
+        data: &[u8],
+        max_chunk_size: usize,
+    ) -> Result<()> {
+        self.encode_segmented_at(chunk_type, data, max_chunk_size, InsertPosition::BeforeIend)
+    }
+
+    /// Like [`Png::encode_segmented`], but places the new chunks, in order, at the given position
+    pub fn encode_segmented_at(
+        &mut self,
+        chunk_type: &str,
+        data: &[u8],
+        max_chunk_size: usize,
+        position: InsertPosition,
+    ) -> Result<()> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let parts = segment::split(data, max_chunk_size)?;
+        let index = self.insertion_index(position);
+        if index > self.chunks.len() {
+            return Err(PngmeError::IndexOutOfRange {
+                index,
+                len: self.chunks.len(),
+            });
+        }
+        self.chunks.splice(
+            index..index,
+            parts
+                .into_iter()
+                .map(|part| Chunk::new(chunk_type.clone(), part)),
+        );
+        Ok(())
+    }
+
+    /// Reassembles the bytes stored under the given chunk type, whether they were split across
+    /// several chunks or not
+    pub fn decode_segmented(&self, chunk_type: &str) -> Result<Vec<u8>> {
+        let parsed_type = ChunkType::from_str(chunk_type)?;
+        let mut parts = self
+            .chunks
+            .iter()
+            .filter(|chunk| *chunk.chunk_type() == parsed_type)
+            .map(Chunk::data)
+            .peekable();
+        if parts.peek().is_none() {
+            return Err(PngmeError::ChunkNotFound(chunk_type.to_string()));
+        }
+        segment::join(parts)
+    }
+
+    /// Stores data the way `pngme encode` does: wrapped in an [`Envelope`], compressed and
+    /// encrypted as asked, and split into chunks of at most
+    /// [`segment::DEFAULT_MAX_CHUNK_SIZE`] bytes right before IEND
+    pub fn encode_payload(
+        &mut self,
+        chunk_type: &str,
+        data: &[u8],
+        compress: bool,
+        password: Option<&str>,
+    ) -> Result<()> {
+        let envelope = Envelope::seal(data, None, compress, password)?;
+        self.encode_segmented(
+            chunk_type,
+            &envelope.to_bytes(),
+            segment::DEFAULT_MAX_CHUNK_SIZE,
+        )
+    }
+
+    /// Reads back data stored by [`Png::encode_payload`] or `pngme encode`. Data stored without
+    /// an envelope is read as [`envelope::open_bare`] describes.
+    pub fn decode_payload(&self, chunk_type: &str, password: Option<&str>) -> Result<Vec<u8>> {
+        envelope::open_payload(&self.decode_segmented(chunk_type)?, password).map(|(data, _)| data)
+    }
+
+    /// Encrypts a message with a passphrase and stores it as a new chunk of the given type, right
+    /// before IEND
+    pub fn encode_encrypted(
+        &mut self,
+        chunk_type: &str,
+        message: &[u8],
+        password: &str,
+    ) -> Result<()> {
+        let data = crypto::encrypt(message, password)?;
+        self.encode_bytes(chunk_type, &data)
+    }
+
+    /// Decrypts the message stored under the given chunk type
+    pub fn decode_encrypted(&self, chunk_type: &str, password: &str) -> Result<Vec<u8>> {
+        crypto::decrypt(&self.decode_segmented(chunk_type)?, password)
+    }
+
+    /// Parses every tEXt, zTXt and iTXt chunk, in file order
+    pub fn text_entries(&self) -> Result<Vec<TextEntry>> {
+        self.chunks
+            .iter()
+            .filter(|chunk| TextEntry::is_text_chunk(chunk.chunk_type()))
+            .map(TextEntry::try_from)
+            .collect()
+    }
+
+    /// Stores a text entry, replacing any existing textual chunks with the same keyword.
+    ///
+    /// The new chunk goes right before IEND, since decoders stop reading there.
+    pub fn set_text(&mut self, entry: &TextEntry) -> Result<()> {
+        let chunk = entry.to_chunk()?;
+        self.chunks.retain(|chunk| {
+            !TextEntry::is_text_chunk(chunk.chunk_type())
+                || TextEntry::try_from(chunk)
+                    .map_or(true, |existing| existing.keyword() != entry.keyword())
+        });
+        self.insert_chunk(chunk, InsertPosition::BeforeIend)
+    }
+
+    /// The index a chunk inserted at the given position ends up at
+    fn insertion_index(&self, position: InsertPosition) -> usize {
+        let find = |chunk_type: &[u8; 4]| {
+            self.chunks
+                .iter()
+                .position(|chunk| &chunk.chunk_type().bytes() == chunk_type)
+        };
+        match position {
+            InsertPosition::BeforeIend => find(b"IEND").unwrap_or(self.chunks.len()),
+            InsertPosition::AfterIhdr => find(b"IHDR").map_or(0, |index| index + 1),
+            InsertPosition::AtIndex(index) => index,
+        }
+    }
+
+    /// Serializes the PNG as the signature followed by every chunk
+    pub fn as_bytes(&self) -> Vec<u8> {
+        Self::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(self.chunks.iter().flat_map(|chunk| chunk.as_bytes()))
+            .collect()
+    }
+}
+
+impl TryFrom<&[u8]> for Png {
+    type Error = PngmeError;
+
+    fn try_from(value: &[u8]) -> Result<Self> {
+        if !value.starts_with(&Self::STANDARD_HEADER) {
+            return Err(PngmeError::MissingSignature);
+        }
+
+        let mut chunks = Vec::new();
+        let mut rest = &value[8..];
+        while !rest.is_empty() {
+            let offset = value.len() - rest.len();
+            if rest.len() < Chunk::METADATA_BYTES {
+                return Err(PngmeError::TruncatedChunk { offset });
+            }
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let chunk_end = length
+                .checked_add(Chunk::METADATA_BYTES)
+                .filter(|end| *end <= rest.len())
+                .ok_or(PngmeError::TruncatedChunk { offset })?;
+            let (chunk_bytes, remaining) = rest.split_at(chunk_end);
+            chunks.push(Chunk::try_from(chunk_bytes)?);
+            rest = remaining;
+        }
+
+        Ok(Png::from_chunks(chunks))
+    }
+}
+
+impl<'a> IntoIterator for &'a Png {
+    type Item = &'a Chunk;
+    type IntoIter = std::slice::Iter<'a, Chunk>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chunks.iter()
+    }
+}
+
+impl Display for Png {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Png {{")?;
+        for chunk in &self.chunks {
+            writeln!(
+                f,
+                "  {} ({} bytes, crc {})",
+                chunk.chunk_type(),
+                chunk.length(),
+                chunk.crc()
+            )?;
+        }
+        writeln!(f, "}}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let data: Vec<u8> = data.bytes().collect();
+        Ok(Chunk::new(chunk_type, data))
+    }
+
+    fn testing_chunks() -> Vec<Chunk> {
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
+        ]
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(testing_chunks())
+    }
+
+    #[test]
+    pub fn test_from_chunks() {
+        let chunks = testing_chunks();
+        let png = Png::from_chunks(chunks);
+
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    pub fn test_valid_from_bytes() {
+        let chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        let bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .chain(chunk_bytes.iter())
+            .copied()
+            .collect();
+
+        let png = Png::try_from(bytes.as_ref());
+
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn test_invalid_header() {
+        let chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        let bytes: Vec<u8> = [13, 80, 78, 71, 13, 10, 26, 10]
+            .iter()
+            .chain(chunk_bytes.iter())
+            .copied()
+            .collect();
+
+        let png = Png::try_from(bytes.as_ref());
+
+        assert!(matches!(png, Err(PngmeError::MissingSignature)));
+    }
+
+    #[test]
+    pub fn test_empty_input() {
+        assert!(Png::try_from([].as_ref()).is_err());
+        assert!(Png::try_from(&Png::STANDARD_HEADER[..4]).is_err());
+    }
+
+    #[test]
+    pub fn test_invalid_chunk() {
+        let mut chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        #[rustfmt::skip]
+        let mut bad_chunk = vec![
+            0, 0, 0, 5,         // length
+            32, 117, 83, 116,   // Chunk Type (bad)
+            65, 64, 65, 66, 67, // Data
+            1, 2, 3, 4, 5       // CRC (bad)
+        ];
+
+        chunk_bytes.append(&mut bad_chunk);
+
+        let png = Png::try_from(chunk_bytes.as_ref());
+
+        assert!(png.is_err());
+    }
+
+    #[test]
+    pub fn test_truncated_chunk() {
+        let bytes = testing_png().as_bytes();
+
+        assert!(matches!(
+            Png::try_from(&bytes[..bytes.len() - 1]),
+            Err(PngmeError::TruncatedChunk { offset: 70 })
+        ));
+        assert!(Png::try_from(&bytes[..bytes.len() - 20]).is_err());
+        assert!(Png::try_from(&bytes[..Png::STANDARD_HEADER.len() + 6]).is_err());
+    }
+
+    #[test]
+    pub fn test_bogus_chunk_length() {
+        let mut bytes = Png::STANDARD_HEADER.to_vec();
+        bytes.extend_from_slice(&u32::MAX.to_be_bytes());
+        bytes.extend_from_slice(b"RuSt");
+        bytes.extend_from_slice(&[0; 8]);
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    pub fn test_from_file() {
+        let path = std::env::temp_dir().join(format!("pngme-png-{}.png", std::process::id()));
+        std::fs::write(&path, testing_png().as_bytes()).unwrap();
+        let png = Png::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(png.unwrap().as_bytes(), testing_png().as_bytes());
+        assert!(matches!(Png::from_file(&path), Err(PngmeError::Io(_))));
+    }
+
+    #[test]
+    pub fn test_list_chunks() {
+        let png = testing_png();
+        let chunks = png.chunks();
+        assert_eq!(chunks.len(), 3);
+    }
+
+    #[test]
+    pub fn test_chunk_by_type() {
+        let png = testing_png();
+        let chunk = png.chunk_by_type("FrSt").unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "FrSt");
+        assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
+    }
+
+    #[test]
+    pub fn test_chunk_by_missing_type() {
+        let png = testing_png();
+        assert!(png.chunk_by_type("TeSt").is_none());
+    }
+
+    #[test]
+    pub fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am a second chunk").unwrap());
+
+        let data: Vec<&[u8]> = png.chunks_by_type("miDl").map(Chunk::data).collect();
+        assert_eq!(data, [&b"I am another chunk"[..], b"I am a second chunk"]);
+        assert_eq!(png.chunks_by_type("TeSt").count(), 0);
+        assert_eq!(png.chunks_by_type("mi1l").count(), 0);
+        assert_eq!(png.position_of("miDl"), Some(1));
+        assert_eq!(png.position_of("TeSt"), None);
+        assert_eq!((&png).into_iter().count(), 4);
+    }
+
+    #[test]
+    pub fn test_find() {
+        let png = testing_png();
+        let found = png.find(|chunk| chunk.chunk_type().bytes().starts_with(b"mi"));
+        assert_eq!(found.unwrap().data(), b"I am another chunk");
+        assert!(png.find(|chunk| chunk.length() == 0).is_none());
+        // The chunk is borrowed from the PNG, not copied
+        assert!(std::ptr::eq(found.unwrap(), &png.chunks()[1]));
+    }
+
+    #[test]
+    pub fn test_chunk_data() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am a second chunk").unwrap());
+
+        assert_eq!(png.chunk_data("miDl", 0).unwrap(), b"I am another chunk");
+        assert_eq!(png.chunk_data("miDl", 1).unwrap(), b"I am a second chunk");
+        assert!(matches!(
+            png.chunk_data("miDl", 2),
+            Err(PngmeError::ChunkNotFound(missing)) if missing == "miDl #2"
+        ));
+        assert!(png.chunk_data("mi1l", 0).is_err());
+    }
+
+    #[test]
+    pub fn test_append_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        let chunk = png.chunk_by_type("TeSt").unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "TeSt");
+        assert_eq!(&chunk.data_as_string().unwrap(), "Message");
+    }
+
+    #[test]
+    pub fn test_insert_chunk_positions() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "").unwrap(),
+            chunk_from_strings("IDAT", "").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        png.encode_bytes("BeFr", b"").unwrap();
+        png.insert_chunk(
+            chunk_from_strings("AfTr", "").unwrap(),
+            InsertPosition::AfterIhdr,
+        )
+        .unwrap();
+        png.encode_segmented_at(
+            "InDx",
+            b"A twenty byte record",
+            18,
+            InsertPosition::AtIndex(0),
+        )
+        .unwrap();
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(
+            types,
+            ["InDx", "InDx", "InDx", "InDx", "IHDR", "AfTr", "IDAT", "BeFr", "IEND"]
+        );
+        assert_eq!(
+            png.decode_segmented("InDx").unwrap(),
+            b"A twenty byte record"
+        );
+    }
+
+    #[test]
+    pub fn test_insert_chunk_out_of_range() {
+        let mut png = testing_png();
+        let chunk = chunk_from_strings("TeSt", "Message").unwrap();
+
+        assert!(matches!(
+            png.insert_chunk_at(4, chunk.clone()),
+            Err(PngmeError::IndexOutOfRange { index: 4, len: 3 })
+        ));
+        png.insert_chunk_at(3, chunk).unwrap();
+        assert_eq!(
+            png.chunks().last().unwrap().chunk_type().to_string(),
+            "TeSt"
+        );
+    }
+
+    #[test]
+    pub fn test_remove_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        let removed = png.remove_chunk("TeSt").unwrap();
+        let chunk = png.chunk_by_type("TeSt");
+        assert_eq!(&removed.data_as_string().unwrap(), "Message");
+        assert!(chunk.is_none());
+    }
+
+    #[test]
+    pub fn test_remove_all_chunks() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "One").unwrap());
+        png.append_chunk(chunk_from_strings("TeSt", "Two").unwrap());
+
+        let removed = png.remove_all_chunks("TeSt").unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[1].data(), b"Two");
+        assert_eq!(png.chunks().len(), 3);
+        assert!(matches!(
+            png.remove_all_chunks("TeSt"),
+            Err(PngmeError::ChunkNotFound(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_retain_chunks() {
+        let mut png = testing_png();
+        let removed = png.retain_chunks(|chunk| chunk.chunk_type().is_critical());
+
+        let kept: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(kept, ["FrSt", "LASt"]);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].chunk_type().to_string(), "miDl");
+        assert!(png.retain_chunks(|_| true).is_empty());
+    }
+
+    #[test]
+    pub fn test_remove_chunk_at() {
+        let mut png = testing_png();
+        let removed = png.remove_chunk_at(1).unwrap();
+
+        assert_eq!(removed.chunk_type().to_string(), "miDl");
+        assert_eq!(png.chunks().len(), 2);
+        assert!(matches!(
+            png.remove_chunk_at(2),
+            Err(PngmeError::IndexOutOfRange { index: 2, len: 2 })
+        ));
+    }
+
+    #[test]
+    pub fn test_remove_missing_chunk() {
+        let mut png = testing_png();
+        assert!(matches!(
+            png.remove_chunk("TeSt"),
+            Err(PngmeError::ChunkNotFound(_))
+        ));
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    pub fn test_encode_bytes() {
+        let mut png = testing_png();
+        let payload = [0, 159, 146, 150, 255];
+        png.encode_bytes("TeSt", &payload).unwrap();
+
+        let mut png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(png.chunk_by_type("TeSt").unwrap().data(), payload);
+        assert!(png.encode_bytes("Te5t", &payload).is_err());
+    }
+
+    #[test]
+    pub fn test_segmented_round_trip() {
+        let mut png = testing_png();
+        let payload: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        png.encode_segmented("TeSt", &payload, 100).unwrap();
+
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(png.chunks().len(), 3 + 12);
+        assert_eq!(png.decode_segmented("TeSt").unwrap(), payload);
+        assert!(matches!(
+            png.decode_segmented("NoNe"),
+            Err(PngmeError::ChunkNotFound(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_segmented_small_payload() {
+        let mut png = testing_png();
+        png.encode_segmented("TeSt", b"Message", 100).unwrap();
+
+        assert_eq!(png.chunk_by_type("TeSt").unwrap().data(), b"Message");
+        assert_eq!(png.decode_segmented("TeSt").unwrap(), b"Message");
+    }
+
+    #[test]
+    pub fn test_segmented_payload_starting_with_magic() {
+        let mut png = testing_png();
+        let payload = b"pSEG\0\0\0\x02\0\0\0\x05 is not a part";
+        png.encode_segmented("TeSt", payload, 100).unwrap();
+
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.decode_segmented("TeSt").unwrap(), payload);
+    }
+
+    #[test]
+    pub fn test_payload_round_trip() {
+        let mut png = testing_png();
+        png.encode_payload("TeSt", b"Message", true, Some("secret"))
+            .unwrap();
+
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert!(envelope::is_envelope(
+            png.chunk_by_type("TeSt").unwrap().data()
+        ));
+        assert_eq!(
+            png.decode_payload("TeSt", Some("secret")).unwrap(),
+            b"Message"
+        );
+        assert!(matches!(
+            png.decode_payload("TeSt", None),
+            Err(PngmeError::PasswordRequired)
+        ));
+    }
+
+    #[test]
+    pub fn test_set_text() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        png.set_text(&TextEntry::new("Title", "First", false).unwrap())
+            .unwrap();
+        png.set_text(&TextEntry::new("Author", "Me", true).unwrap())
+            .unwrap();
+        png.set_text(&TextEntry::new("Title", "Second", false).unwrap())
+            .unwrap();
+
+        let entries = png.text_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].keyword(), "Author");
+        assert_eq!(entries[1].text(), "Second");
+        assert_eq!(
+            png.chunks().last().unwrap().chunk_type().to_string(),
+            "IEND"
+        );
+    }
+
+    #[test]
+    pub fn test_encrypted_round_trip() {
+        let mut png = testing_png();
+        png.encode_encrypted("TeSt", b"Message", "hunter2").unwrap();
+
+        let chunk = png.chunk_by_type("TeSt").unwrap();
+        assert_ne!(chunk.data(), b"Message");
+        assert_eq!(png.decode_encrypted("TeSt", "hunter2").unwrap(), b"Message");
+        assert!(matches!(
+            png.decode_encrypted("TeSt", "wrong"),
+            Err(PngmeError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    pub fn test_as_bytes_round_trip() {
+        let png = testing_png();
+        let bytes = png.as_bytes();
+        let parsed = Png::try_from(bytes.as_ref()).unwrap();
+
+        assert_eq!(&bytes[..8], &Png::STANDARD_HEADER);
+        assert_eq!(parsed.as_bytes(), bytes);
+    }
+
+    #[test]
+    pub fn test_png_trait_impls() {
+        let png = testing_png();
+        let _png_string = format!("{}", png);
+    }
+}
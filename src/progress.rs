@@ -0,0 +1,14 @@
+//! Progress of long operations, reported as [`tracing`] events.
+//!
+//! Reading and writing the pixels for LSB embedding and reading or checking large files report how
+//! far they have got as TRACE events with the target [`PROGRESS_TARGET`] and the fields
+//! `operation`, `done` and `total`. Subscribe to them to draw a progress bar. Without a subscriber
+//! they cost next to nothing.
+
+/// Target of every progress event
+pub const PROGRESS_TARGET: &str = "pngme::progress";
+
+/// Reports that `done` of the `total` steps of an operation are finished
+pub fn report(operation: &'static str, done: usize, total: usize) {
+    tracing::trace!(target: PROGRESS_TARGET, operation, done, total);
+}
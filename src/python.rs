@@ -0,0 +1,342 @@
+//! Bindings for Python, built into a `pngme` extension module with `maturin build --release`
+//! (see `pyproject.toml`). PNGs go in and come out as `bytes`, and the `Png`, `Chunk` and
+//! `ChunkType` classes wrap the types of the same name.
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::scan::Scanner;
+
+create_exception!(
+    pngme,
+    PngmeError,
+    PyException,
+    "Raised when a PNG cannot be read, or a chunk is invalid or missing"
+);
+
+impl From<crate::Error> for PyErr {
+    fn from(err: crate::Error) -> PyErr {
+        PngmeError::new_err(err.to_string())
+    }
+}
+
+/// A four letter chunk type, e.g. `ChunkType("ruSt")`
+#[pyclass(
+    name = "ChunkType",
+    module = "pngme",
+    frozen,
+    eq,
+    hash,
+    skip_from_py_object
+)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PyChunkType(ChunkType);
+
+#[pymethods]
+impl PyChunkType {
+    #[new]
+    fn new(chunk_type: &str) -> PyResult<Self> {
+        Ok(PyChunkType(ChunkType::from_str(chunk_type)?))
+    }
+
+    /// Whether decoders must understand the chunk to display the image
+    #[getter]
+    fn is_critical(&self) -> bool {
+        self.0.is_critical()
+    }
+
+    /// Whether the chunk type is part of the PNG specification
+    #[getter]
+    fn is_public(&self) -> bool {
+        self.0.is_public()
+    }
+
+    /// Whether editors that do not understand the chunk may copy it into a modified image
+    #[getter]
+    fn is_safe_to_copy(&self) -> bool {
+        self.0.is_safe_to_copy()
+    }
+
+    /// Whether the reserved bit is unset, as the PNG specification requires
+    #[getter]
+    fn is_valid(&self) -> bool {
+        self.0.is_valid()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ChunkType('{}')", self.0)
+    }
+}
+
+/// A chunk of a PNG, e.g. `Chunk("ruSt", b"hello")`
+#[pyclass(name = "Chunk", module = "pngme", frozen, from_py_object)]
+#[derive(Debug, Clone)]
+pub struct PyChunk(Chunk);
+
+#[pymethods]
+impl PyChunk {
+    #[new]
+    fn new(chunk_type: &str, data: &[u8]) -> PyResult<Self> {
+        Ok(PyChunk(Chunk::new(
+            ChunkType::from_str(chunk_type)?,
+            data.to_vec(),
+        )))
+    }
+
+    #[getter]
+    fn chunk_type(&self) -> PyChunkType {
+        PyChunkType(self.0.chunk_type().clone())
+    }
+
+    #[getter]
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.0.data())
+    }
+
+    /// Number of bytes in the data field
+    #[getter]
+    fn length(&self) -> u32 {
+        self.0.length()
+    }
+
+    /// The CRC stored with the chunk
+    #[getter]
+    fn crc(&self) -> u32 {
+        self.0.crc()
+    }
+
+    /// Whether the stored CRC matches the chunk type and data
+    #[getter]
+    fn crc_valid(&self) -> bool {
+        self.0.is_crc_valid()
+    }
+
+    /// The chunk as it appears in a file: length, type, data and CRC
+    fn __bytes__(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.0.as_bytes())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Chunk('{}', {} bytes)",
+            self.0.chunk_type(),
+            self.0.length()
+        )
+    }
+}
+
+/// A PNG read from bytes, e.g. `Png(open("image.png", "rb").read())`
+#[pyclass(name = "Png", module = "pngme", skip_from_py_object)]
+#[derive(Debug, Clone)]
+pub struct PyPng(Png);
+
+#[pymethods]
+impl PyPng {
+    #[new]
+    fn new(png: &[u8]) -> PyResult<Self> {
+        Ok(PyPng(Png::try_from(png)?))
+    }
+
+    /// Reads a PNG from a file
+    #[cfg(feature = "fs")]
+    #[staticmethod]
+    fn from_file(path: std::path::PathBuf) -> PyResult<Self> {
+        Ok(PyPng(Png::from_file(path)?))
+    }
+
+    /// The chunks in file order
+    #[getter]
+    fn chunks(&self) -> Vec<PyChunk> {
+        self.0.chunks().iter().cloned().map(PyChunk).collect()
+    }
+
+    /// The first chunk of a type, or None
+    fn chunk_by_type(&self, chunk_type: &str) -> Option<PyChunk> {
+        self.0.chunk_by_type(chunk_type).cloned().map(PyChunk)
+    }
+
+    /// Adds a chunk after the last one
+    fn append_chunk(&mut self, chunk: PyChunk) {
+        self.0.append_chunk(chunk.0);
+    }
+
+    /// Removes the first chunk of a type and returns it
+    fn remove_chunk(&mut self, chunk_type: &str) -> PyResult<PyChunk> {
+        Ok(PyChunk(self.0.remove_chunk(chunk_type)?))
+    }
+
+    /// Stores data under a chunk type right before IEND, compressed and wrapped in an envelope
+    /// as `pngme encode` stores it
+    fn encode(&mut self, chunk_type: &str, data: &[u8]) -> PyResult<()> {
+        Ok(self.0.encode_payload(chunk_type, data, true, None)?)
+    }
+
+    /// Reads the data stored under a chunk type by `encode` or `pngme encode`, joining it back
+    /// together if it was split across several chunks
+    fn decode(&self, chunk_type: &str) -> PyResult<Cow<'_, [u8]>> {
+        Ok(Cow::Owned(self.0.decode_payload(chunk_type, None)?))
+    }
+
+    /// Describes every problem with the chunk CRCs and the placement of IHDR and IEND
+    fn validate(&self) -> Vec<String> {
+        self.0
+            .validate()
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect()
+    }
+
+    /// The PNG as it would be written to a file
+    fn __bytes__(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.0.as_bytes())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Png({} chunks)", self.0.chunks().len())
+    }
+}
+
+/// Where a chunk sits in a file and whether it is intact, as returned by `scan`
+#[pyclass(
+    name = "ChunkInfo",
+    module = "pngme",
+    frozen,
+    get_all,
+    skip_from_py_object
+)]
+#[derive(Debug, Clone)]
+pub struct PyChunkInfo {
+    /// Position of the chunk among all chunks in the file
+    index: usize,
+    chunk_type: PyChunkType,
+    /// Number of bytes in the chunk's data field
+    length: u32,
+    /// Byte offset of the chunk's length field from the start of the file
+    offset: usize,
+    /// The CRC stored in the file
+    crc: u32,
+    /// Whether the stored CRC matches the chunk type and data
+    crc_valid: bool,
+}
+
+/// Stores `data` under `chunk_type` right before IEND, compressed and wrapped in an envelope as
+/// `pngme encode` stores it, and returns the new PNG
+#[pyfunction]
+pub fn encode(png: &[u8], chunk_type: &str, data: &[u8]) -> PyResult<Cow<'static, [u8]>> {
+    let mut png = Png::try_from(png)?;
+    png.encode_payload(chunk_type, data, true, None)?;
+    Ok(Cow::Owned(png.as_bytes()))
+}
+
+/// Reads the bytes stored under `chunk_type` by `encode` or `pngme encode`, joining them back
+/// together if they were split across several chunks
+#[pyfunction]
+pub fn decode(png: &[u8], chunk_type: &str) -> PyResult<Cow<'static, [u8]>> {
+    Ok(Cow::Owned(
+        Png::try_from(png)?.decode_payload(chunk_type, None)?,
+    ))
+}
+
+/// Lists every chunk with its offset and CRC status. Chunks with a bad CRC are listed rather
+/// than raising, so damaged files can be audited
+#[pyfunction]
+pub fn scan(png: &[u8]) -> PyResult<Vec<PyChunkInfo>> {
+    let infos = Scanner::new(png)?
+        .map(|info| {
+            info.map(|info| PyChunkInfo {
+                index: info.index,
+                chunk_type: PyChunkType(info.chunk_type),
+                length: info.length,
+                offset: info.offset,
+                crc: info.crc,
+                crc_valid: info.crc_valid,
+            })
+        })
+        .collect::<crate::Result<_>>()?;
+    Ok(infos)
+}
+
+#[pymodule]
+fn pngme(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyPng>()?;
+    m.add_class::<PyChunk>()?;
+    m.add_class::<PyChunkType>()?;
+    m.add_class::<PyChunkInfo>()?;
+    m.add_function(wrap_pyfunction!(encode, m)?)?;
+    m.add_function(wrap_pyfunction!(decode, m)?)?;
+    m.add_function(wrap_pyfunction!(scan, m)?)?;
+    m.add("PngmeError", m.py().get_type::<PngmeError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope;
+
+    fn testing_png() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+        .as_bytes()
+    }
+
+    // Nothing here needs the interpreter, so the bindings are called like plain functions
+    #[test]
+    pub fn test_bindings() {
+        let encoded = encode(&testing_png(), "ruSt", b"hello").unwrap();
+        assert_eq!(decode(&encoded, "ruSt").unwrap().as_ref(), b"hello");
+
+        let infos = scan(&encoded).unwrap();
+        let types: Vec<String> = infos.iter().map(|info| info.chunk_type.__str__()).collect();
+        assert_eq!(types, ["IHDR", "ruSt", "IEND"]);
+        assert_eq!(infos[1].offset, 33);
+        assert!(infos.iter().all(|info| info.crc_valid));
+    }
+
+    #[test]
+    pub fn test_png_class() {
+        let mut png = PyPng::new(&testing_png()).unwrap();
+        png.encode("ruSt", b"hello").unwrap();
+        png.append_chunk(PyChunk::new("teSt", b"extra").unwrap());
+
+        assert_eq!(png.decode("ruSt").unwrap().as_ref(), b"hello");
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.remove_chunk("teSt").unwrap().data().as_ref(), b"extra");
+        assert!(png.chunk_by_type("teSt").is_none());
+        assert!(png.validate().is_empty());
+        assert_eq!(png.__repr__(), "Png(3 chunks)");
+        assert!(PyChunkType::new("ruSt").unwrap().is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_payloads_match_the_library() {
+        let encoded = encode(&testing_png(), "ruSt", b"from python").unwrap();
+        let png = Png::try_from(encoded.as_ref()).unwrap();
+        assert_eq!(png.decode_payload("ruSt", None).unwrap(), b"from python");
+        assert!(envelope::is_envelope(
+            &png.decode_segmented("ruSt").unwrap()
+        ));
+
+        let mut png = Png::try_from(testing_png().as_slice()).unwrap();
+        png.encode_payload("ruSt", b"from rust", true, None)
+            .unwrap();
+        assert_eq!(
+            decode(&png.as_bytes(), "ruSt").unwrap().as_ref(),
+            b"from rust"
+        );
+        assert_eq!(PyPng(png).decode("ruSt").unwrap().as_ref(), b"from rust");
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::Read;
+
+use crate::chunk_type::ChunkType;
+use crate::stream::PngReader;
+use crate::Result;
+
+/// Where a chunk sits in a file and whether it is intact, without the chunk data itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkInfo {
+    /// Position of the chunk among all chunks in the file
+    pub index: usize,
+    pub chunk_type: ChunkType,
+    /// Number of bytes in the chunk's data field
+    pub length: u32,
+    /// Byte offset of the chunk's length field from the start of the file
+    pub offset: usize,
+    /// The CRC stored in the file
+    pub crc: u32,
+    /// Whether the stored CRC matches the chunk type and data
+    pub crc_valid: bool,
+}
+
+/// Walks every chunk of a PNG, collecting a [`ChunkInfo`] for each.
+///
+/// Chunks with a bad CRC are reported rather than treated as errors; a truncated or otherwise
+/// unreadable chunk ends the scan with an error.
+pub struct Scanner<R: Read> {
+    reader: PngReader<R>,
+    index: usize,
+}
+
+impl<R: Read> Scanner<R> {
+    /// Starts a scan, validating the PNG signature
+    pub fn new(reader: R) -> Result<Self> {
+        Ok(Scanner {
+            reader: PngReader::new(reader)?.without_crc_check(),
+            index: 0,
+        })
+    }
+}
+
+impl<R: Read> Iterator for Scanner<R> {
+    type Item = Result<ChunkInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.reader.offset();
+        let chunk = match self.reader.next()? {
+            Ok(chunk) => chunk,
+            Err(err) => return Some(Err(err)),
+        };
+        let info = ChunkInfo {
+            index: self.index,
+            chunk_type: chunk.chunk_type().clone(),
+            length: chunk.length(),
+            offset,
+            crc: chunk.crc(),
+            crc_valid: chunk.is_crc_valid(),
+        };
+        self.index += 1;
+        Some(Ok(info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::png::Png;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hidden".to_vec()),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+        .as_bytes()
+    }
+
+    #[test]
+    pub fn test_scan_offsets() {
+        let bytes = testing_bytes();
+        let infos: Vec<ChunkInfo> = Scanner::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        let offsets: Vec<usize> = infos.iter().map(|info| info.offset).collect();
+        assert_eq!(offsets, vec![8, 8 + 25, 8 + 25 + 18]);
+        assert_eq!(infos[1].index, 1);
+        assert_eq!(infos[1].length, 6);
+        assert!(infos.iter().all(|info| info.crc_valid));
+    }
+
+    #[test]
+    pub fn test_scan_reports_bad_crc() {
+        let mut bytes = testing_bytes();
+        // Last byte of the ruSt chunk's CRC
+        bytes[8 + 25 + 17] ^= 1;
+        let infos: Vec<ChunkInfo> = Scanner::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(infos.len(), 3);
+        assert!(!infos[1].crc_valid);
+        assert!(infos[2].crc_valid);
+    }
+
+    #[test]
+    pub fn test_scan_truncated() {
+        let bytes = testing_bytes();
+        let results: Vec<Result<ChunkInfo>> = Scanner::new(&bytes[..40]).unwrap().collect();
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+}
@@ -0,0 +1,232 @@
+//! Where the passphrases that encrypt payloads and key their HMACs come from. A passphrase passed
+//! with `--password` or `--hmac-key` ends up in shell history and in the process list for other
+//! users to see, so it can also be read from a file, an environment variable or the terminal.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use pngme::{PngmeError, Result};
+
+use crate::args::{HmacKeyArgs, PasswordArgs};
+use crate::output;
+
+/// The passphrase given by whichever of the options was used, if any. With `confirm` a passphrase
+/// typed at the prompt has to be typed twice, so a typo cannot lock the payload away for good.
+pub fn password(args: &PasswordArgs, confirm: bool) -> Result<Option<String>> {
+    let source = Source {
+        value: &args.password,
+        env: &args.password_env,
+        ask: args.ask_password,
+        file: &args.key_file,
+    };
+    source.read("passphrase", confirm)
+}
+
+/// The passphrase that keys the HMAC, given by whichever of the --hmac-key options was used
+pub fn hmac_key(args: &HmacKeyArgs, confirm: bool) -> Result<Option<String>> {
+    let source = Source {
+        value: &args.hmac_key,
+        env: &args.hmac_key_env,
+        ask: args.ask_hmac_key,
+        file: &args.hmac_key_file,
+    };
+    source.read("HMAC passphrase", confirm)
+}
+
+/// The options a secret can be given with. Clap lets at most one of them be used.
+struct Source<'a> {
+    value: &'a Option<String>,
+    env: &'a Option<String>,
+    ask: bool,
+    file: &'a Option<PathBuf>,
+}
+
+impl Source<'_> {
+    fn read(&self, name: &str, confirm: bool) -> Result<Option<String>> {
+        let secret = if let Some(secret) = self.value {
+            secret.clone()
+        } else if let Some(var) = self.env {
+            env::var(var)
+                .map_err(|err| PngmeError::InvalidArgument(format!("${}: {}", var, err)))?
+        } else if self.ask {
+            prompt(name, confirm)?
+        } else if let Some(path) = self.file {
+            read_key_file(path)?
+        } else {
+            return Ok(None);
+        };
+        if secret.is_empty() {
+            return Err(PngmeError::InvalidArgument(format!(
+                "the {} is empty",
+                name
+            )));
+        }
+        Ok(Some(secret))
+    }
+}
+
+/// The passphrase in the key file named by the config file. Options on the command line win over
+/// it, so it is only read once [`password`] has come up empty.
+pub fn default_password(args: &PasswordArgs) -> Result<Option<String>> {
+    let Some(path) = &args.default_key_file else {
+        return Ok(None);
+    };
+    match read_key_file(path)? {
+        password if password.is_empty() => Err(PngmeError::InvalidArgument(format!(
+            "{}: the passphrase is empty",
+            path.display()
+        ))),
+        password => Ok(Some(password)),
+    }
+}
+
+/// Reads a passphrase from a file, without its trailing newline
+fn read_key_file(path: &Path) -> Result<String> {
+    let contents = fs::read_to_string(path).map_err(|err| {
+        PngmeError::Io(io::Error::new(
+            err.kind(),
+            format!("{}: {}", path.display(), err),
+        ))
+    })?;
+    Ok(contents
+        .strip_suffix('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .unwrap_or(&contents)
+        .to_string())
+}
+
+/// Reads a passphrase from the terminal without echoing it
+fn prompt(name: &str, confirm: bool) -> Result<String> {
+    output::clear_progress();
+    let label = name[..1].to_uppercase() + &name[1..];
+    let password = rpassword::prompt_password(format!("{}: ", label))?;
+    if confirm && rpassword::prompt_password(format!("Repeat {}: ", name))? != password {
+        return Err(PngmeError::InvalidArgument(
+            "the passphrases do not match".to_string(),
+        ));
+    }
+    Ok(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn password_args() -> PasswordArgs {
+        PasswordArgs {
+            password: None,
+            key_file: None,
+            password_env: None,
+            ask_password: false,
+            default_key_file: None,
+        }
+    }
+
+    fn hmac_key_args() -> HmacKeyArgs {
+        HmacKeyArgs {
+            hmac_key: None,
+            hmac_key_file: None,
+            hmac_key_env: None,
+            ask_hmac_key: false,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("pngme-secrets-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    pub fn test_password_sources() {
+        assert_eq!(password(&password_args(), false).unwrap(), None);
+
+        let args = PasswordArgs {
+            password: Some("argv".to_string()),
+            ..password_args()
+        };
+        assert_eq!(password(&args, false).unwrap().as_deref(), Some("argv"));
+
+        let var = format!("PNGME_TEST_PASSWORD_{}", std::process::id());
+        env::set_var(&var, "from env");
+        let args = PasswordArgs {
+            password_env: Some(var.clone()),
+            ..password_args()
+        };
+        assert_eq!(password(&args, false).unwrap().as_deref(), Some("from env"));
+        env::remove_var(&var);
+        assert!(matches!(
+            password(&args, false),
+            Err(PngmeError::InvalidArgument(_))
+        ));
+
+        let path = temp_path("key");
+        fs::write(&path, "from file\r\n").unwrap();
+        let args = PasswordArgs {
+            key_file: Some(path.clone()),
+            ..password_args()
+        };
+        assert_eq!(
+            password(&args, false).unwrap().as_deref(),
+            Some("from file")
+        );
+
+        let args = PasswordArgs {
+            default_key_file: Some(path.clone()),
+            ..password_args()
+        };
+        assert_eq!(password(&args, false).unwrap(), None);
+        assert_eq!(
+            default_password(&args).unwrap().as_deref(),
+            Some("from file")
+        );
+        fs::remove_file(&path).unwrap();
+
+        let args = PasswordArgs {
+            password: Some(String::new()),
+            ..password_args()
+        };
+        assert!(matches!(
+            password(&args, false),
+            Err(PngmeError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_hmac_key_sources() {
+        assert_eq!(hmac_key(&hmac_key_args(), false).unwrap(), None);
+
+        let args = HmacKeyArgs {
+            hmac_key: Some("argv".to_string()),
+            ..hmac_key_args()
+        };
+        assert_eq!(hmac_key(&args, false).unwrap().as_deref(), Some("argv"));
+
+        let var = format!("PNGME_TEST_HMAC_KEY_{}", std::process::id());
+        env::set_var(&var, "from env");
+        let args = HmacKeyArgs {
+            hmac_key_env: Some(var.clone()),
+            ..hmac_key_args()
+        };
+        assert_eq!(hmac_key(&args, false).unwrap().as_deref(), Some("from env"));
+        env::remove_var(&var);
+
+        let path = temp_path("hmac-key");
+        fs::write(&path, "from file\n").unwrap();
+        let args = HmacKeyArgs {
+            hmac_key_file: Some(path.clone()),
+            ..hmac_key_args()
+        };
+        assert_eq!(
+            hmac_key(&args, false).unwrap().as_deref(),
+            Some("from file")
+        );
+
+        fs::write(&path, "").unwrap();
+        assert_eq!(
+            hmac_key(&args, false).unwrap_err().to_string(),
+            PngmeError::InvalidArgument("the HMAC passphrase is empty".to_string()).to_string()
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
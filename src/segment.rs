@@ -0,0 +1,206 @@
+use crate::chunk::Chunk;
+use crate::error::PngmeError;
+use crate::Result;
+
+/// Marks a chunk as one part of a payload that was split across several chunks
+pub const SEGMENT_MAGIC: &[u8; 4] = b"pSEG";
+
+/// Size of the header in front of every part: magic, part index and part count
+pub const SEGMENT_HEADER_BYTES: usize = 12;
+
+/// Payloads larger than this are split across several chunks by default
+pub const DEFAULT_MAX_CHUNK_SIZE: usize = 1 << 20;
+
+/// Splits a payload into chunk-sized parts.
+///
+/// A payload that fits in `max_chunk_size` bytes is returned as a single part without a header, so
+/// small payloads are stored exactly as before. Larger payloads are split into parts that each
+/// start with [`SEGMENT_MAGIC`], the part index and the part count (big-endian `u32`s). A payload
+/// that itself starts with [`SEGMENT_MAGIC`] always gets the header, even as a single part, so
+/// [`join`] does not mistake it for a part.
+pub fn split(data: &[u8], max_chunk_size: usize) -> Result<Vec<Vec<u8>>> {
+    if max_chunk_size > Chunk::MAX_LENGTH {
+        return Err(PngmeError::InvalidSegment(format!(
+            "max chunk size must be at most {} bytes, the PNG limit on chunk length",
+            Chunk::MAX_LENGTH
+        )));
+    }
+    if data.len() <= max_chunk_size && !data.starts_with(SEGMENT_MAGIC) {
+        return Ok(vec![data.to_vec()]);
+    }
+    if max_chunk_size <= SEGMENT_HEADER_BYTES {
+        return Err(PngmeError::InvalidSegment(format!(
+            "max chunk size must be larger than the {} byte segment header",
+            SEGMENT_HEADER_BYTES
+        )));
+    }
+
+    let parts = data.chunks(max_chunk_size - SEGMENT_HEADER_BYTES);
+    let count = u32::try_from(parts.len())
+        .map_err(|_| PngmeError::InvalidSegment("payload needs too many parts".to_string()))?;
+    Ok(parts
+        .enumerate()
+        .map(|(index, part)| {
+            SEGMENT_MAGIC
+                .iter()
+                .copied()
+                .chain((index as u32).to_be_bytes())
+                .chain(count.to_be_bytes())
+                .chain(part.iter().copied())
+                .collect()
+        })
+        .collect())
+}
+
+/// Checks whether chunk data is one part of a split payload
+pub fn is_segment(data: &[u8]) -> bool {
+    data.len() >= SEGMENT_HEADER_BYTES && data.starts_with(SEGMENT_MAGIC)
+}
+
+/// Reassembles a payload from the data of every chunk it was stored in, in any order.
+///
+/// If the first part is not a segment it is returned as is, matching how unsplit payloads are
+/// stored.
+pub fn join<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> Result<Vec<u8>> {
+    let mut parts = parts.into_iter().peekable();
+    match parts.peek() {
+        None => return Err(PngmeError::InvalidSegment("no parts to join".to_string())),
+        Some(first) if !is_segment(first) => return Ok(first.to_vec()),
+        Some(_) => {}
+    }
+
+    let mut segments = parts
+        .map(|part| {
+            if !is_segment(part) {
+                return Err(PngmeError::InvalidSegment(
+                    "payload mixes split and unsplit chunks".to_string(),
+                ));
+            }
+            let index = u32::from_be_bytes(part[4..8].try_into().unwrap());
+            let count = u32::from_be_bytes(part[8..12].try_into().unwrap());
+            Ok((index, count, &part[SEGMENT_HEADER_BYTES..]))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    segments.sort_by_key(|(index, _, _)| *index);
+
+    let count = segments[0].1;
+    let mut payload = Vec::new();
+    for expected in 0..count {
+        let (index, part_count, data) =
+            segments
+                .get(expected as usize)
+                .ok_or(PngmeError::MissingSegment {
+                    index: expected,
+                    count,
+                })?;
+        if *part_count != count {
+            return Err(PngmeError::InvalidSegment(format!(
+                "parts disagree on the part count ({} and {})",
+                count, part_count
+            )));
+        }
+        if *index != expected {
+            return Err(match *index < expected {
+                true => PngmeError::InvalidSegment(format!("part {} appears twice", index)),
+                false => PngmeError::MissingSegment {
+                    index: expected,
+                    count,
+                },
+            });
+        }
+        payload.extend_from_slice(data);
+    }
+    if segments.len() > count as usize {
+        return Err(PngmeError::InvalidSegment(format!(
+            "found {} parts but the payload has {}",
+            segments.len(),
+            count
+        )));
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload() -> Vec<u8> {
+        (0..=255).cycle().take(1000).collect()
+    }
+
+    #[test]
+    pub fn test_small_payload_is_not_split() {
+        let parts = split(b"small", 100).unwrap();
+        assert_eq!(parts, vec![b"small".to_vec()]);
+        assert!(!is_segment(&parts[0]));
+        assert_eq!(join(parts.iter().map(Vec::as_slice)).unwrap(), b"small");
+    }
+
+    #[test]
+    pub fn test_payload_starting_with_magic() {
+        for data in [&b"pSEG"[..], b"pSEG\0\0\0\x07\0\0\0\x09 looks like a part"] {
+            let parts = split(data, 100).unwrap();
+            assert_eq!(parts.len(), 1);
+            assert!(is_segment(&parts[0]));
+            assert_eq!(join(parts.iter().map(Vec::as_slice)).unwrap(), data);
+        }
+
+        let mut data = SEGMENT_MAGIC.to_vec();
+        data.extend(payload());
+        let parts = split(&data, 300).unwrap();
+        assert_eq!(parts.len(), 4);
+        assert_eq!(join(parts.iter().map(Vec::as_slice)).unwrap(), data);
+    }
+
+    #[test]
+    pub fn test_split_and_join() {
+        let parts = split(&payload(), 112).unwrap();
+
+        assert_eq!(parts.len(), 10);
+        assert!(parts
+            .iter()
+            .all(|part| part.len() <= 112 && is_segment(part)));
+        assert_eq!(join(parts.iter().map(Vec::as_slice)).unwrap(), payload());
+    }
+
+    #[test]
+    pub fn test_join_out_of_order() {
+        let parts = split(&payload(), 300).unwrap();
+        let joined = join(parts.iter().rev().map(Vec::as_slice)).unwrap();
+        assert_eq!(joined, payload());
+    }
+
+    #[test]
+    pub fn test_join_missing_part() {
+        let mut parts = split(&payload(), 300).unwrap();
+        parts.remove(1);
+        assert!(matches!(
+            join(parts.iter().map(Vec::as_slice)),
+            Err(PngmeError::MissingSegment { index: 1, count: 4 })
+        ));
+    }
+
+    #[test]
+    pub fn test_join_duplicate_part() {
+        let mut parts = split(&payload(), 300).unwrap();
+        parts.push(parts[2].clone());
+        assert!(matches!(
+            join(parts.iter().map(Vec::as_slice)),
+            Err(PngmeError::InvalidSegment(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_split_rejects_tiny_chunk_size() {
+        assert!(split(&payload(), SEGMENT_HEADER_BYTES).is_err());
+    }
+
+    #[test]
+    pub fn test_split_rejects_chunk_size_over_png_limit() {
+        assert_eq!(split(b"small", Chunk::MAX_LENGTH).unwrap().len(), 1);
+        assert!(matches!(
+            split(b"small", Chunk::MAX_LENGTH + 1),
+            Err(PngmeError::InvalidSegment(_))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+use std::str::FromStr;
+
+use serde::de::Error as _;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// Chunk types are written as their four letter code, e.g. `"ruSt"`
+impl Serialize for ChunkType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChunkType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        ChunkType::from_str(&code).map_err(D::Error::custom)
+    }
+}
+
+/// Chunks are written with their type, length, data and stored CRC
+impl Serialize for Chunk {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut chunk = serializer.serialize_struct("Chunk", 4)?;
+        chunk.serialize_field("chunk_type", self.chunk_type())?;
+        chunk.serialize_field("length", &self.length())?;
+        chunk.serialize_field("data", self.data())?;
+        chunk.serialize_field("crc", &self.crc())?;
+        chunk.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct ChunkFields {
+    chunk_type: ChunkType,
+    data: Vec<u8>,
+    crc: Option<u32>,
+}
+
+/// The length is always taken from the data. A CRC is optional, but one that is given has to
+/// match the data.
+impl<'de> Deserialize<'de> for Chunk {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = ChunkFields::deserialize(deserializer)?;
+        match fields.crc {
+            Some(crc) => {
+                Chunk::from_parts(fields.chunk_type, fields.data, crc).map_err(D::Error::custom)
+            }
+            None => Ok(Chunk::new(fields.chunk_type, fields.data)),
+        }
+    }
+}
+
+impl Serialize for Png {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut png = serializer.serialize_struct("Png", 1)?;
+        png.serialize_field("chunks", self.chunks())?;
+        png.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct PngFields {
+    chunks: Vec<Chunk>,
+}
+
+impl<'de> Deserialize<'de> for Png {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Png::from_chunks(
+            PngFields::deserialize(deserializer)?.chunks,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::ColorType;
+    use crate::scan::{ChunkInfo, Scanner};
+    use crate::validate::ValidationIssue;
+    use serde_json::json;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hi".to_vec()),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+    }
+
+    #[test]
+    pub fn test_png_round_trip() {
+        let value = serde_json::to_value(testing_png()).unwrap();
+        let chunk = &value["chunks"][1];
+
+        assert_eq!(chunk["chunk_type"], "ruSt");
+        assert_eq!(chunk["length"], 2);
+        assert_eq!(chunk["data"], json!([104, 105]));
+        let png: Png = serde_json::from_value(value).unwrap();
+        assert_eq!(png.as_bytes(), testing_png().as_bytes());
+    }
+
+    #[test]
+    pub fn test_chunk_checks() {
+        let chunk: Chunk =
+            serde_json::from_value(json!({ "chunk_type": "ruSt", "data": [104, 105] })).unwrap();
+        assert!(chunk.is_crc_valid());
+
+        let bad_crc = json!({ "chunk_type": "ruSt", "data": [104, 105], "crc": 1 });
+        assert!(serde_json::from_value::<Chunk>(bad_crc).is_err());
+        let bad_type = json!({ "chunk_type": "ru5t", "data": [] });
+        assert!(serde_json::from_value::<Chunk>(bad_type).is_err());
+    }
+
+    #[test]
+    pub fn test_report_serialization() {
+        let issue = ValidationIssue::MisplacedIend { index: 3 };
+        assert_eq!(
+            serde_json::to_value(&issue).unwrap(),
+            json!({ "kind": "misplaced_iend", "index": 3 })
+        );
+
+        let bytes = testing_png().as_bytes();
+        let info = Scanner::new(bytes.as_slice())
+            .unwrap()
+            .nth(1)
+            .unwrap()
+            .unwrap();
+        let value = serde_json::to_value(&info).unwrap();
+        assert_eq!(value["chunk_type"], "ruSt");
+        assert_eq!(value["crc_valid"], true);
+        assert_eq!(serde_json::from_value::<ChunkInfo>(value).unwrap(), info);
+
+        assert_eq!(
+            serde_json::to_value(ColorType::GrayscaleAlpha).unwrap(),
+            "grayscale_alpha"
+        );
+    }
+}
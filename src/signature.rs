@@ -0,0 +1,325 @@
+use std::str::FromStr;
+
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use ed25519_dalek::{Signature, Signer, Verifier};
+use sha2::{Digest, Sha256};
+
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::PngmeError;
+use crate::png::{InsertPosition, Png};
+use crate::Result;
+
+/// Chunk type that signatures are stored under: ancillary, private and safe to copy
+pub const SIGNATURE_CHUNK_TYPE: &str = "siGn";
+
+/// Size of a signature chunk's data: the chunk type of the signed payload, the SHA-256 digest of
+/// the payload and the signature
+pub const SIGNATURE_CHUNK_BYTES: usize = 4 + DIGEST_BYTES + Signature::BYTE_SIZE;
+
+const DIGEST_BYTES: usize = 32;
+
+/// A chunk together with an Ed25519 signature over its type and data
+#[derive(Debug, Clone)]
+pub struct SignedChunk {
+    chunk: Chunk,
+    signature: Signature,
+}
+
+impl SignedChunk {
+    /// Signs a chunk's type and data
+    pub fn sign(chunk: Chunk, key: &SigningKey) -> SignedChunk {
+        let signature = key.sign(&signed_message(chunk.chunk_type(), chunk.data()));
+        SignedChunk { chunk, signature }
+    }
+
+    /// Pairs a chunk with the signature chunk stored for it, checking that the signature matches
+    pub fn verify(
+        chunk: Chunk,
+        signature_chunk: &Chunk,
+        key: &VerifyingKey,
+    ) -> Result<SignedChunk> {
+        let (signed_type, digest, signature) = parse_signature_chunk(signature_chunk)?;
+        if signed_type != *chunk.chunk_type() || *digest != Sha256::digest(chunk.data())[..] {
+            return Err(PngmeError::InvalidSignature);
+        }
+        key.verify(
+            &signed_message(chunk.chunk_type(), chunk.data()),
+            &signature,
+        )
+        .map_err(|_| PngmeError::InvalidSignature)?;
+        Ok(SignedChunk { chunk, signature })
+    }
+
+    /// The chunk that was signed
+    pub fn chunk(&self) -> &Chunk {
+        &self.chunk
+    }
+
+    /// The chunk that stores the signature alongside the signed chunk
+    pub fn signature_chunk(&self) -> Chunk {
+        signature_chunk(self.chunk.chunk_type(), self.chunk.data(), &self.signature)
+    }
+
+    /// Unwraps the signed chunk
+    pub fn into_inner(self) -> Chunk {
+        self.chunk
+    }
+}
+
+impl Png {
+    /// Signs the payload stored under a chunk type and stores the signature right before IEND,
+    /// replacing any earlier signature for the same payload.
+    ///
+    /// `data` is the payload exactly as stored, after any compression and encryption, so the
+    /// signature covers what is in the file. The signature is looked up by chunk type and payload
+    /// digest, so named payloads sharing a chunk type each keep their own.
+    pub fn sign_payload(&mut self, chunk_type: &str, data: &[u8], key: &SigningKey) -> Result<()> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let signature = key.sign(&signed_message(&chunk_type, data));
+        if let Some(index) = self.signature_index(&chunk_type, data) {
+            self.remove_chunk_at(index)?;
+        }
+        self.insert_chunk(
+            signature_chunk(&chunk_type, data, &signature),
+            InsertPosition::BeforeIend,
+        )
+    }
+
+    /// Checks the payload stored under a chunk type against the signature stored for it
+    pub fn verify_payload(&self, chunk_type: &str, data: &[u8], key: &VerifyingKey) -> Result<()> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let index = match self.signature_index(&chunk_type, data) {
+            Some(index) => index,
+            // A signature for the chunk type that matches no payload means the payload changed
+            None if self.signatures(&chunk_type).next().is_some() => {
+                return Err(PngmeError::InvalidSignature)
+            }
+            None => return Err(PngmeError::SignatureNotFound(chunk_type.to_string())),
+        };
+        let (_, _, signature) = parse_signature_chunk(&self.chunks()[index])?;
+        key.verify(&signed_message(&chunk_type, data), &signature)
+            .map_err(|_| PngmeError::InvalidSignature)
+    }
+
+    /// The signature chunks made for a chunk type
+    fn signatures<'a>(&'a self, chunk_type: &'a ChunkType) -> impl Iterator<Item = &'a Chunk> {
+        self.chunks()
+            .iter()
+            .filter(move |chunk| signed_type(chunk).as_ref() == Some(chunk_type))
+    }
+
+    /// The index of the signature chunk made for a payload stored under a chunk type
+    fn signature_index(&self, chunk_type: &ChunkType, data: &[u8]) -> Option<usize> {
+        let digest = Sha256::digest(data);
+        self.chunks()
+            .iter()
+            .position(|chunk| signs(chunk, chunk_type, &digest))
+    }
+
+    /// Removes the signature chunks made for a payload stored under a chunk type
+    pub(crate) fn remove_payload_signatures(&mut self, chunk_type: &ChunkType, data: &[u8]) {
+        let digest = Sha256::digest(data);
+        self.retain_chunks(|chunk| !signs(chunk, chunk_type, &digest));
+    }
+}
+
+/// Reads an Ed25519 private key from a PKCS#8 PEM document
+pub fn read_signing_key(pem: &str) -> Result<SigningKey> {
+    SigningKey::from_pkcs8_pem(pem).map_err(|err| PngmeError::InvalidKey(err.to_string()))
+}
+
+/// Reads an Ed25519 public key from a SubjectPublicKeyInfo PEM document
+pub fn read_verifying_key(pem: &str) -> Result<VerifyingKey> {
+    VerifyingKey::from_public_key_pem(pem).map_err(|err| PngmeError::InvalidKey(err.to_string()))
+}
+
+/// The bytes that get signed: the chunk type followed by the data, so a signature cannot be
+/// moved onto a payload stored under another chunk type
+fn signed_message(chunk_type: &ChunkType, data: &[u8]) -> Vec<u8> {
+    chunk_type.bytes().iter().chain(data).copied().collect()
+}
+
+fn signature_chunk(chunk_type: &ChunkType, data: &[u8], signature: &Signature) -> Chunk {
+    let data = chunk_type
+        .bytes()
+        .iter()
+        .chain(Sha256::digest(data).iter())
+        .chain(signature.to_bytes().iter())
+        .copied()
+        .collect();
+    Chunk::new(
+        ChunkType::from_str(SIGNATURE_CHUNK_TYPE).expect("the signature chunk type is valid"),
+        data,
+    )
+}
+
+/// The chunk type a signature chunk was made for, if the chunk is a signature chunk
+fn signed_type(chunk: &Chunk) -> Option<ChunkType> {
+    if chunk.chunk_type().to_string() != SIGNATURE_CHUNK_TYPE
+        || chunk.data().len() != SIGNATURE_CHUNK_BYTES
+    {
+        return None;
+    }
+    ChunkType::try_from(<[u8; 4]>::try_from(&chunk.data()[..4]).unwrap()).ok()
+}
+
+/// Whether a chunk is the signature made for a payload with this digest under a chunk type
+fn signs(chunk: &Chunk, chunk_type: &ChunkType, digest: &[u8]) -> bool {
+    signed_type(chunk).as_ref() == Some(chunk_type) && chunk.data()[4..4 + DIGEST_BYTES] == *digest
+}
+
+fn parse_signature_chunk(chunk: &Chunk) -> Result<(ChunkType, &[u8], Signature)> {
+    let signed_type = signed_type(chunk).ok_or(PngmeError::InvalidSignature)?;
+    let (digest, signature) = chunk.data()[4..].split_at(DIGEST_BYTES);
+    let signature = Signature::from_slice(signature).map_err(|_| PngmeError::InvalidSignature)?;
+    Ok((signed_type, digest, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+    use ed25519_dalek::pkcs8::{EncodePrivateKey, EncodePublicKey};
+
+    fn testing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn testing_png() -> Png {
+        let chunk = |chunk_type: &str, data: &[u8]| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+        };
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("ruSt", b"Message"),
+            chunk("IEND", b""),
+        ])
+    }
+
+    #[test]
+    pub fn test_signed_chunk() {
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"Message".to_vec());
+        let signed = SignedChunk::sign(chunk.clone(), &testing_key());
+        let signature_chunk = signed.signature_chunk();
+        let key = testing_key().verifying_key();
+
+        assert_eq!(signature_chunk.chunk_type().to_string(), "siGn");
+        assert!(SignedChunk::verify(chunk, &signature_chunk, &key).is_ok());
+
+        let tampered = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"Massage".to_vec());
+        assert!(matches!(
+            SignedChunk::verify(tampered, &signature_chunk, &key),
+            Err(PngmeError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    pub fn test_sign_payload() {
+        let mut png = testing_png();
+        png.sign_payload("ruSt", b"Message", &testing_key())
+            .unwrap();
+        png.sign_payload("ruSt", b"Message", &testing_key())
+            .unwrap();
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        let key = testing_key().verifying_key();
+
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.chunks()[2].chunk_type().to_string(), "siGn");
+        assert!(png.verify_payload("ruSt", b"Message", &key).is_ok());
+        assert!(matches!(
+            png.verify_payload("ruSt", b"Massage", &key),
+            Err(PngmeError::InvalidSignature)
+        ));
+        assert!(matches!(
+            png.verify_payload(
+                "ruSt",
+                b"Message",
+                &SigningKey::from_bytes(&[8; 32]).verifying_key()
+            ),
+            Err(PngmeError::InvalidSignature)
+        ));
+        assert!(matches!(
+            png.verify_payload("teSt", b"Message", &key),
+            Err(PngmeError::SignatureNotFound(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_sign_payloads_sharing_a_chunk_type() {
+        let mut png = testing_png();
+        png.encode_named("one", "ruSt", b"First", 100, InsertPosition::BeforeIend)
+            .unwrap();
+        png.sign_payload("ruSt", b"First", &testing_key()).unwrap();
+        png.encode_named("two", "ruSt", b"Second", 100, InsertPosition::BeforeIend)
+            .unwrap();
+        png.sign_payload("ruSt", b"Second", &testing_key()).unwrap();
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        let key = testing_key().verifying_key();
+
+        for name in ["one", "two"] {
+            let manifest = png.manifest().unwrap();
+            let entry = manifest.get(name).unwrap();
+            let payload = crate::segment::join(png.payload_chunks(entry).unwrap()).unwrap();
+            assert!(png.verify_payload("ruSt", &payload, &key).is_ok());
+        }
+        assert_eq!(
+            png.chunks()
+                .iter()
+                .filter(|chunk| chunk.chunk_type().to_string() == "siGn")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    pub fn test_replacing_a_named_payload_drops_its_signature() {
+        let signatures = |png: &Png| {
+            png.chunks()
+                .iter()
+                .filter(|chunk| chunk.chunk_type().to_string() == "siGn")
+                .count()
+        };
+        let mut png = testing_png();
+        let key = testing_key();
+        png.encode_named("one", "ruSt", b"First", 100, InsertPosition::BeforeIend)
+            .unwrap();
+        png.sign_payload("ruSt", b"First", &key).unwrap();
+        png.encode_named("one", "ruSt", b"Second", 100, InsertPosition::BeforeIend)
+            .unwrap();
+        assert_eq!(signatures(&png), 0);
+        png.sign_payload("ruSt", b"Second", &key).unwrap();
+        assert_eq!(signatures(&png), 1);
+
+        // A signature still covering another payload with the same bytes stays
+        png.encode_named("two", "ruSt", b"Second", 100, InsertPosition::BeforeIend)
+            .unwrap();
+        png.encode_named("one", "ruSt", b"Third", 100, InsertPosition::BeforeIend)
+            .unwrap();
+        assert_eq!(signatures(&png), 1);
+        let key = key.verifying_key();
+        assert!(png.verify_payload("ruSt", b"Second", &key).is_ok());
+    }
+
+    #[test]
+    pub fn test_read_pem_keys() {
+        let key = testing_key();
+        let private_pem = key.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let public_pem = key
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+
+        assert_eq!(read_signing_key(&private_pem).unwrap(), key);
+        assert_eq!(
+            read_verifying_key(&public_pem).unwrap(),
+            key.verifying_key()
+        );
+        assert!(matches!(
+            read_signing_key(&public_pem),
+            Err(PngmeError::InvalidKey(_))
+        ));
+    }
+}
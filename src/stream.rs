@@ -0,0 +1,245 @@
+use std::io::{self, Read, Write};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::PngmeError;
+use crate::png::Png;
+use crate::Result;
+
+/// Reads a PNG from any `Read` one chunk at a time, so the whole file never has to be in memory.
+///
+/// The signature is checked when the reader is created. Iteration stops after the first error.
+pub struct PngReader<R: Read> {
+    reader: R,
+    offset: usize,
+    check_crc: bool,
+    done: bool,
+}
+
+impl<R: Read> PngReader<R> {
+    /// Wraps a reader, consuming and validating the PNG signature
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut signature = [0; 8];
+        reader
+            .read_exact(&mut signature)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => PngmeError::MissingSignature,
+                _ => PngmeError::Io(err),
+            })?;
+        if signature != Png::STANDARD_HEADER {
+            return Err(PngmeError::MissingSignature);
+        }
+        Ok(PngReader {
+            reader,
+            offset: signature.len(),
+            check_crc: true,
+            done: false,
+        })
+    }
+
+    /// Yields chunks whose stored CRC does not match their data instead of failing on them, so
+    /// they can be inspected with [`Chunk::is_crc_valid`]
+    pub fn without_crc_check(mut self) -> Self {
+        self.check_crc = false;
+        self
+    }
+
+    /// Byte offset of the next chunk in the underlying stream
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the wrapped reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_chunk(&mut self) -> Result<Option<Chunk>> {
+        let mut header = [0; 8];
+        let read = read_fully(&mut self.reader, &mut header)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        let truncated = PngmeError::TruncatedChunk {
+            offset: self.offset,
+        };
+        if read < header.len() {
+            return Err(truncated);
+        }
+
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap());
+        let chunk_type = ChunkType::try_from(<[u8; 4]>::try_from(&header[4..]).unwrap())?;
+
+        // Grow the buffer as data arrives instead of trusting the length field up front.
+        let mut data = Vec::new();
+        (&mut self.reader)
+            .take(length as u64)
+            .read_to_end(&mut data)?;
+        let mut crc = [0; 4];
+        if data.len() < length as usize || read_fully(&mut self.reader, &mut crc)? < crc.len() {
+            return Err(truncated);
+        }
+
+        let crc = u32::from_be_bytes(crc);
+        let chunk = match self.check_crc {
+            true => Chunk::from_parts(chunk_type, data, crc)?,
+            false => Chunk::from_parts_unchecked(chunk_type, data, crc),
+        };
+        self.offset += Chunk::METADATA_BYTES + length as usize;
+        Ok(Some(chunk))
+    }
+}
+
+impl<R: Read> Iterator for PngReader<R> {
+    type Item = Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.read_chunk().transpose();
+        if !matches!(next, Some(Ok(_))) {
+            self.done = true;
+        }
+        next
+    }
+}
+
+/// Writes a PNG to any `Write` one chunk at a time
+pub struct PngWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PngWriter<W> {
+    /// Wraps a writer, writing the PNG signature to it
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(&Png::STANDARD_HEADER)?;
+        Ok(PngWriter { writer })
+    }
+
+    /// Writes a single chunk
+    pub fn write_chunk(&mut self, chunk: &Chunk) -> Result<()> {
+        self.writer.write_all(&chunk.length().to_be_bytes())?;
+        self.writer.write_all(&chunk.chunk_type().bytes())?;
+        self.writer.write_all(chunk.data())?;
+        self.writer.write_all(&chunk.crc().to_be_bytes())?;
+        Ok(())
+    }
+
+    /// Flushes and returns the wrapped writer
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Like `read_exact`, but returns how many bytes were read instead of failing at end of input
+fn read_fully(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("FrSt").unwrap(), b"first".to_vec()),
+            Chunk::new(ChunkType::from_str("miDl").unwrap(), Vec::new()),
+            Chunk::new(ChunkType::from_str("LASt").unwrap(), b"last".to_vec()),
+        ])
+    }
+
+    #[test]
+    pub fn test_reader_yields_every_chunk() {
+        let bytes = testing_png().as_bytes();
+        let reader = PngReader::new(bytes.as_slice()).unwrap();
+        let chunks: Vec<Chunk> = reader.collect::<Result<_>>().unwrap();
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].chunk_type().to_string(), "FrSt");
+        assert_eq!(chunks[2].data(), b"last");
+    }
+
+    #[test]
+    pub fn test_reader_tracks_offset() {
+        let bytes = testing_png().as_bytes();
+        let mut reader = PngReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.offset(), 8);
+
+        reader.next().unwrap().unwrap();
+        assert_eq!(reader.offset(), 8 + 12 + 5);
+    }
+
+    #[test]
+    pub fn test_reader_rejects_missing_signature() {
+        assert!(matches!(
+            PngReader::new(&b"GIF89a"[..]),
+            Err(PngmeError::MissingSignature)
+        ));
+        assert!(matches!(
+            PngReader::new(&b"not a png file"[..]),
+            Err(PngmeError::MissingSignature)
+        ));
+    }
+
+    #[test]
+    pub fn test_reader_reports_truncation_and_stops() {
+        let bytes = testing_png().as_bytes();
+        let mut reader = PngReader::new(&bytes[..bytes.len() - 2]).unwrap();
+
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(
+            reader.next(),
+            Some(Err(PngmeError::TruncatedChunk { offset: 37 }))
+        ));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    pub fn test_reader_reports_bad_crc() {
+        let mut bytes = testing_png().as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        let result: Result<Vec<Chunk>> = PngReader::new(bytes.as_slice()).unwrap().collect();
+
+        assert!(matches!(result, Err(PngmeError::InvalidCrc { .. })));
+    }
+
+    #[test]
+    pub fn test_reader_without_crc_check() {
+        let mut bytes = testing_png().as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        let reader = PngReader::new(bytes.as_slice())
+            .unwrap()
+            .without_crc_check();
+        let chunks: Vec<Chunk> = reader.collect::<Result<_>>().unwrap();
+
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[1].is_crc_valid());
+        assert!(!chunks[2].is_crc_valid());
+    }
+
+    #[test]
+    pub fn test_writer_matches_png_as_bytes() {
+        let png = testing_png();
+        let mut writer = PngWriter::new(Vec::new()).unwrap();
+        for chunk in png.chunks() {
+            writer.write_chunk(chunk).unwrap();
+        }
+
+        assert_eq!(writer.finish().unwrap(), png.as_bytes());
+    }
+}
@@ -0,0 +1,299 @@
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::compression::{deflate, inflate};
+use crate::error::PngmeError;
+use crate::Result;
+
+/// Which of the three textual chunk types an entry is stored in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextKind {
+    /// tEXt: uncompressed Latin-1 text
+    Text,
+    /// zTXt: zlib-compressed Latin-1 text
+    CompressedText,
+    /// iTXt: UTF-8 text, optionally compressed, with a language tag and translated keyword
+    InternationalText {
+        compressed: bool,
+        language_tag: String,
+        translated_keyword: String,
+    },
+}
+
+impl TextKind {
+    /// The chunk type code this kind of entry is stored under
+    pub fn chunk_type(&self) -> &'static str {
+        match self {
+            TextKind::Text => "tEXt",
+            TextKind::CompressedText => "zTXt",
+            TextKind::InternationalText { .. } => "iTXt",
+        }
+    }
+}
+
+/// A keyword/value pair from a tEXt, zTXt or iTXt chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEntry {
+    keyword: String,
+    text: String,
+    kind: TextKind,
+}
+
+impl TextEntry {
+    /// Creates an entry, picking tEXt (or zTXt when compressed) if the text fits in Latin-1 and
+    /// iTXt otherwise
+    pub fn new(keyword: &str, text: &str, compressed: bool) -> Result<TextEntry> {
+        validate_keyword(keyword)?;
+        let kind = match (is_latin1(text), compressed) {
+            (true, false) => TextKind::Text,
+            (true, true) => TextKind::CompressedText,
+            (false, compressed) => TextKind::InternationalText {
+                compressed,
+                language_tag: String::new(),
+                translated_keyword: String::new(),
+            },
+        };
+        Ok(TextEntry {
+            keyword: keyword.to_string(),
+            text: text.to_string(),
+            kind,
+        })
+    }
+
+    /// The keyword identifying what the text is, e.g. `Title` or `Author`
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    /// The text itself, decompressed and decoded
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Which chunk type the entry is stored in
+    pub fn kind(&self) -> &TextKind {
+        &self.kind
+    }
+
+    /// Checks whether a chunk type is one of the textual chunk types
+    pub fn is_text_chunk(chunk_type: &ChunkType) -> bool {
+        matches!(&chunk_type.bytes(), b"tEXt" | b"zTXt" | b"iTXt")
+    }
+
+    /// Serializes the entry as a chunk
+    pub fn to_chunk(&self) -> Result<Chunk> {
+        let mut data = latin1_bytes(&self.keyword)?;
+        data.push(0);
+        match &self.kind {
+            TextKind::Text => data.extend(latin1_bytes(&self.text)?),
+            TextKind::CompressedText => {
+                data.push(0);
+                data.extend(deflate(&latin1_bytes(&self.text)?));
+            }
+            TextKind::InternationalText {
+                compressed,
+                language_tag,
+                translated_keyword,
+            } => {
+                data.extend([*compressed as u8, 0]);
+                data.extend(language_tag.as_bytes());
+                data.push(0);
+                data.extend(translated_keyword.as_bytes());
+                data.push(0);
+                match compressed {
+                    true => data.extend(deflate(self.text.as_bytes())),
+                    false => data.extend(self.text.as_bytes()),
+                }
+            }
+        }
+        Ok(Chunk::new(
+            ChunkType::from_str(self.kind.chunk_type())?,
+            data,
+        ))
+    }
+}
+
+impl TryFrom<&Chunk> for TextEntry {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        let (keyword, rest) = split_at_null(chunk.data())
+            .ok_or_else(|| invalid("missing null separator after keyword"))?;
+        let keyword = latin1_string(keyword);
+        validate_keyword(&keyword)?;
+
+        let (text, kind) = match &chunk.chunk_type().bytes() {
+            b"tEXt" => (latin1_string(rest), TextKind::Text),
+            b"zTXt" => {
+                let (method, compressed) = rest
+                    .split_first()
+                    .ok_or_else(|| invalid("missing compression method"))?;
+                check_method(*method)?;
+                (
+                    latin1_string(&inflate(compressed)?),
+                    TextKind::CompressedText,
+                )
+            }
+            b"iTXt" => {
+                let [flag, method, rest @ ..] = rest else {
+                    return Err(invalid("missing compression flag"));
+                };
+                let (language_tag, rest) = split_at_null(rest)
+                    .ok_or_else(|| invalid("missing null separator after language tag"))?;
+                let (translated_keyword, text) = split_at_null(rest)
+                    .ok_or_else(|| invalid("missing null separator after translated keyword"))?;
+                let compressed = match flag {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(invalid("compression flag must be 0 or 1")),
+                };
+                let text = match compressed {
+                    true => {
+                        check_method(*method)?;
+                        inflate(text)?
+                    }
+                    false => text.to_vec(),
+                };
+                let kind = TextKind::InternationalText {
+                    compressed,
+                    language_tag: String::from_utf8(language_tag.to_vec())?,
+                    translated_keyword: String::from_utf8(translated_keyword.to_vec())?,
+                };
+                (String::from_utf8(text)?, kind)
+            }
+            _ => {
+                return Err(invalid(&format!(
+                    "{} is not a textual chunk",
+                    chunk.chunk_type()
+                )))
+            }
+        };
+        Ok(TextEntry {
+            keyword,
+            text,
+            kind,
+        })
+    }
+}
+
+/// Keywords are 1-79 printable Latin-1 characters, without leading, trailing or repeated spaces
+fn validate_keyword(keyword: &str) -> Result<()> {
+    let printable = keyword
+        .chars()
+        .all(|c| matches!(c as u32, 32..=126 | 161..=255));
+    if keyword.is_empty()
+        || keyword.chars().count() > 79
+        || !printable
+        || keyword.starts_with(' ')
+        || keyword.ends_with(' ')
+        || keyword.contains("  ")
+    {
+        return Err(PngmeError::InvalidKeyword(keyword.to_string()));
+    }
+    Ok(())
+}
+
+fn invalid(reason: &str) -> PngmeError {
+    PngmeError::InvalidTextChunk(reason.to_string())
+}
+
+fn check_method(method: u8) -> Result<()> {
+    match method {
+        0 => Ok(()),
+        _ => Err(invalid(&format!("unknown compression method {}", method))),
+    }
+}
+
+fn split_at_null(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let position = data.iter().position(|byte| *byte == 0)?;
+    Some((&data[..position], &data[position + 1..]))
+}
+
+fn is_latin1(text: &str) -> bool {
+    text.chars().all(|c| (c as u32) <= 255)
+}
+
+fn latin1_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| *byte as char).collect()
+}
+
+fn latin1_bytes(text: &str) -> Result<Vec<u8>> {
+    text.chars()
+        .map(|c| u8::try_from(c as u32))
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|_| invalid("text cannot be represented in Latin-1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    #[test]
+    pub fn test_parse_text() {
+        let entry = TextEntry::try_from(&chunk("tEXt", b"Title\0Caf\xe9")).unwrap();
+        assert_eq!(entry.keyword(), "Title");
+        assert_eq!(entry.text(), "Café");
+        assert_eq!(entry.kind(), &TextKind::Text);
+    }
+
+    #[test]
+    pub fn test_round_trip_every_kind() {
+        for (text, compressed, chunk_type) in [
+            ("Café", false, "tEXt"),
+            ("Café", true, "zTXt"),
+            ("日本語", false, "iTXt"),
+            ("日本語", true, "iTXt"),
+        ] {
+            let entry = TextEntry::new("Comment", text, compressed).unwrap();
+            let chunk = entry.to_chunk().unwrap();
+            assert_eq!(chunk.chunk_type().to_string(), chunk_type);
+            assert_eq!(TextEntry::try_from(&chunk).unwrap(), entry);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_international_text() {
+        let entry =
+            TextEntry::try_from(&chunk("iTXt", b"Title\0\0\0ja\0\xe9\xa1\x8c\0Hello")).unwrap();
+        assert_eq!(entry.text(), "Hello");
+        assert_eq!(
+            entry.kind(),
+            &TextKind::InternationalText {
+                compressed: false,
+                language_tag: "ja".to_string(),
+                translated_keyword: "題".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    pub fn test_invalid_keywords() {
+        for keyword in [
+            "",
+            " Title",
+            "Title ",
+            "Two  spaces",
+            &"k".repeat(80),
+            "日本",
+        ] {
+            assert!(matches!(
+                TextEntry::new(keyword, "text", false),
+                Err(PngmeError::InvalidKeyword(_))
+            ));
+        }
+        assert!(TextEntry::new(&"k".repeat(79), "text", false).is_ok());
+    }
+
+    #[test]
+    pub fn test_malformed_chunks() {
+        assert!(TextEntry::try_from(&chunk("tEXt", b"no separator")).is_err());
+        assert!(TextEntry::try_from(&chunk("zTXt", b"Title\0\x05data")).is_err());
+        assert!(TextEntry::try_from(&chunk("iTXt", b"Title\0\x02\0\0\0")).is_err());
+        assert!(TextEntry::try_from(&chunk("ruSt", b"Title\0text")).is_err());
+    }
+}
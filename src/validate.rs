@@ -0,0 +1,499 @@
+use std::fmt::Display;
+
+use crate::borrowed::{ChunkRef, PngRef};
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::progress;
+use crate::stream::PngReader;
+
+/// A problem found while validating a PNG
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum ValidationIssue {
+    /// The file does not start with the PNG signature
+    MissingSignature,
+    /// The bytes from `offset` on could not be read as chunks
+    UnreadableData { offset: usize, reason: String },
+    /// A chunk's stored CRC does not match its type and data
+    BadCrc {
+        index: usize,
+        chunk_type: ChunkType,
+        stored: u32,
+        computed: u32,
+    },
+    /// There is no IHDR chunk
+    MissingIhdr,
+    /// IHDR is not the first chunk
+    MisplacedIhdr { index: usize },
+    /// There is no IEND chunk
+    MissingIend,
+    /// IEND is not the last chunk
+    MisplacedIend { index: usize },
+    /// A chunk that may only appear once appears again
+    DuplicateChunk { index: usize, chunk_type: ChunkType },
+    /// [`Png::from_bytes_lossy`] skipped `length` bytes from `offset` on to reach the next chunk
+    SkippedData {
+        offset: usize,
+        length: usize,
+        reason: String,
+    },
+}
+
+impl ValidationIssue {
+    /// A stable, machine-readable name for the kind of issue
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ValidationIssue::MissingSignature => "missing_signature",
+            ValidationIssue::UnreadableData { .. } => "unreadable_data",
+            ValidationIssue::BadCrc { .. } => "bad_crc",
+            ValidationIssue::MissingIhdr => "missing_ihdr",
+            ValidationIssue::MisplacedIhdr { .. } => "misplaced_ihdr",
+            ValidationIssue::MissingIend => "missing_iend",
+            ValidationIssue::MisplacedIend { .. } => "misplaced_iend",
+            ValidationIssue::DuplicateChunk { .. } => "duplicate_chunk",
+            ValidationIssue::SkippedData { .. } => "skipped_data",
+        }
+    }
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::MissingSignature => write!(f, "missing PNG signature"),
+            ValidationIssue::UnreadableData { offset, reason } => {
+                write!(f, "unreadable data from offset {}: {}", offset, reason)
+            }
+            ValidationIssue::BadCrc {
+                index,
+                chunk_type,
+                stored,
+                computed,
+            } => write!(
+                f,
+                "chunk {} ({}) has CRC {:#010x}, expected {:#010x}",
+                index, chunk_type, stored, computed
+            ),
+            ValidationIssue::MissingIhdr => write!(f, "no IHDR chunk"),
+            ValidationIssue::MisplacedIhdr { index } => {
+                write!(f, "IHDR is chunk {}, it must be the first chunk", index)
+            }
+            ValidationIssue::MissingIend => write!(f, "no IEND chunk"),
+            ValidationIssue::MisplacedIend { index } => {
+                write!(f, "IEND is chunk {}, it must be the last chunk", index)
+            }
+            ValidationIssue::DuplicateChunk { index, chunk_type } => {
+                write!(f, "chunk {} is a second {} chunk", index, chunk_type)
+            }
+            ValidationIssue::SkippedData {
+                offset,
+                length,
+                reason,
+            } => write!(
+                f,
+                "skipped {} byte(s) from offset {}: {}",
+                length, offset, reason
+            ),
+        }
+    }
+}
+
+/// Chunks that must appear exactly once
+const SINGLETONS: [&[u8; 4]; 2] = [b"IHDR", b"IEND"];
+
+impl Png {
+    /// Checks every chunk CRC and the placement of IHDR and IEND
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let chunks: Vec<ChunkRef> = self.chunks().iter().map(ChunkRef::from).collect();
+        validate_chunks(&chunks)
+    }
+
+    /// Recomputes the CRC of every chunk whose stored CRC is wrong, returning how many were fixed
+    pub fn repair_crcs(&mut self) -> usize {
+        let mut repaired = 0;
+        for chunk in self.chunks_mut() {
+            if !chunk.is_crc_valid() {
+                tracing::debug!(chunk_type = %chunk.chunk_type(), "recomputing CRC");
+                *chunk = Chunk::new(chunk.chunk_type().clone(), chunk.data().to_vec());
+                repaired += 1;
+            }
+        }
+        repaired
+    }
+
+    /// Reads a damaged PNG, skipping whatever cannot be read instead of failing.
+    ///
+    /// A chunk with a bad CRC or a length that runs past the end of the file is skipped, and
+    /// reading carries on at the next offset that holds a plausible chunk: a length that fits, a
+    /// type of four ASCII letters and a matching CRC. Everything skipped is reported as
+    /// [`ValidationIssue::SkippedData`], after [`ValidationIssue::MissingSignature`] if the file
+    /// does not start with the signature, in which case chunks are looked for from the start.
+    pub fn from_bytes_lossy(bytes: &[u8]) -> (Png, Vec<ValidationIssue>) {
+        let mut issues = Vec::new();
+        let mut offset = match bytes.starts_with(&Png::STANDARD_HEADER) {
+            true => Png::STANDARD_HEADER.len(),
+            false => {
+                issues.push(ValidationIssue::MissingSignature);
+                0
+            }
+        };
+        let mut chunks = Vec::new();
+        while offset < bytes.len() {
+            match read_chunk_at(bytes, offset) {
+                Ok(chunk) => {
+                    offset += Chunk::METADATA_BYTES + chunk.data().len();
+                    chunks.push(chunk);
+                }
+                Err(reason) => {
+                    let next = (offset + 1..bytes.len())
+                        .find(|&next| read_chunk_at(bytes, next).is_ok())
+                        .unwrap_or(bytes.len());
+                    tracing::debug!(
+                        offset,
+                        skipped = next - offset,
+                        "resynchronized on next chunk"
+                    );
+                    issues.push(ValidationIssue::SkippedData {
+                        offset,
+                        length: next - offset,
+                        reason,
+                    });
+                    offset = next;
+                }
+            }
+            progress::report("reading chunks", offset, bytes.len());
+        }
+        tracing::debug!(chunks = chunks.len(), issues = issues.len(), "read chunks");
+        (Png::from_chunks(chunks), issues)
+    }
+}
+
+/// Reads the chunk starting at an offset, or says why there is no valid chunk there
+fn read_chunk_at(bytes: &[u8], offset: usize) -> Result<Chunk, String> {
+    let rest = &bytes[offset..];
+    if rest.len() < Chunk::METADATA_BYTES {
+        return Err(format!("{} byte(s) are too few for a chunk", rest.len()));
+    }
+    let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+    if length > rest.len() - Chunk::METADATA_BYTES {
+        return Err(format!(
+            "chunk length {} runs past the end of the file",
+            length
+        ));
+    }
+    let type_bytes: [u8; 4] = rest[4..8].try_into().unwrap();
+    let chunk_type = ChunkType::try_from(type_bytes).map_err(|err| err.to_string())?;
+    let data = &rest[8..8 + length];
+    let stored = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+    let computed = Chunk::compute_crc(&chunk_type, data);
+    if stored != computed {
+        return Err(format!(
+            "{} chunk has CRC {:#010x}, expected {:#010x}",
+            chunk_type, stored, computed
+        ));
+    }
+    Ok(Chunk::new(chunk_type, data.to_vec()))
+}
+
+/// Reads as much of a possibly damaged PNG as it can.
+///
+/// Chunks with bad CRCs are kept as they are. Reading stops at the first chunk that cannot be
+/// read, which is reported as [`ValidationIssue::UnreadableData`] along with a missing signature.
+pub fn read_damaged(bytes: &[u8]) -> (Option<Png>, Vec<ValidationIssue>) {
+    let Ok(reader) = PngReader::new(bytes) else {
+        return (None, vec![ValidationIssue::MissingSignature]);
+    };
+    let mut reader = reader.without_crc_check();
+    let mut chunks = Vec::new();
+    let mut issues = Vec::new();
+    loop {
+        let offset = reader.offset();
+        match reader.next() {
+            Some(Ok(chunk)) => {
+                chunks.push(chunk);
+                progress::report("reading chunks", offset, bytes.len());
+            }
+            Some(Err(err)) => {
+                issues.push(ValidationIssue::UnreadableData {
+                    offset,
+                    reason: err.to_string(),
+                });
+                break;
+            }
+            None => break,
+        }
+    }
+    progress::report("reading chunks", bytes.len(), bytes.len());
+    tracing::debug!(chunks = chunks.len(), issues = issues.len(), "read chunks");
+    (Some(Png::from_chunks(chunks)), issues)
+}
+
+/// Validates a whole file: its signature, that every byte belongs to a readable chunk, and
+/// everything [`Png::validate`] checks. Chunk data is checked in place, without being copied.
+pub fn validate_bytes(bytes: &[u8]) -> Vec<ValidationIssue> {
+    let Ok(png) = PngRef::new(bytes) else {
+        return vec![ValidationIssue::MissingSignature];
+    };
+    let mut chunk_refs = png.chunks();
+    let mut chunks = Vec::new();
+    let mut issues = Vec::new();
+    loop {
+        let offset = chunk_refs.offset();
+        match chunk_refs.next() {
+            Some(Ok(chunk)) => {
+                chunks.push(chunk);
+                progress::report("checking chunks", offset, bytes.len());
+            }
+            Some(Err(err)) => {
+                issues.push(ValidationIssue::UnreadableData {
+                    offset,
+                    reason: err.to_string(),
+                });
+                break;
+            }
+            None => break,
+        }
+    }
+    progress::report("checking chunks", bytes.len(), bytes.len());
+    tracing::debug!(chunks = chunks.len(), issues = issues.len(), "read chunks");
+    issues.extend(validate_chunks(&chunks));
+    issues
+}
+
+fn validate_chunks(chunks: &[ChunkRef]) -> Vec<ValidationIssue> {
+    // CRCs are the expensive part for files with thousands of IDAT chunks, and each is
+    // independent of the others, so the parallel feature spreads them across threads. The
+    // placement checks below are a cheap pass over the chunk types and stay sequential
+    #[cfg(feature = "parallel")]
+    let mut issues: Vec<ValidationIssue> = {
+        use rayon::prelude::*;
+        chunks
+            .par_iter()
+            .enumerate()
+            .filter_map(|(index, chunk)| check_crc(index, chunk))
+            .collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let mut issues: Vec<ValidationIssue> = chunks
+        .iter()
+        .enumerate()
+        .filter_map(|(index, chunk)| check_crc(index, chunk))
+        .collect();
+
+    for singleton in SINGLETONS {
+        let positions: Vec<usize> = chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| &chunk.chunk_type().bytes() == singleton)
+            .map(|(index, _)| index)
+            .collect();
+        let Some(&first) = positions.first() else {
+            issues.push(match singleton {
+                b"IHDR" => ValidationIssue::MissingIhdr,
+                _ => ValidationIssue::MissingIend,
+            });
+            continue;
+        };
+        match singleton {
+            b"IHDR" if first != 0 => issues.push(ValidationIssue::MisplacedIhdr { index: first }),
+            b"IEND" if first != chunks.len() - 1 => {
+                issues.push(ValidationIssue::MisplacedIend { index: first })
+            }
+            _ => {}
+        }
+        for &index in &positions[1..] {
+            issues.push(ValidationIssue::DuplicateChunk {
+                index,
+                chunk_type: chunks[index].chunk_type(),
+            });
+        }
+    }
+
+    issues
+}
+
+fn check_crc(index: usize, chunk: &ChunkRef) -> Option<ValidationIssue> {
+    let chunk_type = chunk.chunk_type();
+    let computed = Chunk::compute_crc(&chunk_type, chunk.data());
+    (computed != chunk.crc()).then(|| ValidationIssue::BadCrc {
+        index,
+        chunk_type,
+        stored: chunk.crc(),
+        computed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("IDAT", b"pixels"),
+            chunk("IEND", b""),
+        ])
+    }
+
+    #[test]
+    pub fn test_valid_png() {
+        assert!(testing_png().validate().is_empty());
+        assert!(validate_bytes(&testing_png().as_bytes()).is_empty());
+    }
+
+    #[test]
+    pub fn test_placement_issues() {
+        let png = Png::from_chunks(vec![
+            chunk("IDAT", b"pixels"),
+            chunk("IHDR", &[0; 13]),
+            chunk("IEND", b""),
+            chunk("ruSt", b"after the end"),
+            chunk("IEND", b""),
+        ]);
+
+        assert_eq!(
+            png.validate(),
+            vec![
+                ValidationIssue::MisplacedIhdr { index: 1 },
+                ValidationIssue::MisplacedIend { index: 2 },
+                ValidationIssue::DuplicateChunk {
+                    index: 4,
+                    chunk_type: ChunkType::from_str("IEND").unwrap()
+                },
+            ]
+        );
+        assert_eq!(
+            Png::from_chunks(Vec::new()).validate(),
+            vec![ValidationIssue::MissingIhdr, ValidationIssue::MissingIend]
+        );
+    }
+
+    #[test]
+    pub fn test_bad_crc_and_repair() {
+        let mut bytes = testing_png().as_bytes();
+        // Last byte of the IDAT chunk's CRC
+        bytes[8 + 25 + 17] ^= 1;
+
+        let issues = validate_bytes(&bytes);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(
+            issues[0],
+            ValidationIssue::BadCrc { index: 1, .. }
+        ));
+
+        let (png, _) = read_damaged(&bytes);
+        let mut png = png.unwrap();
+        assert_eq!(png.repair_crcs(), 1);
+        assert!(png.validate().is_empty());
+        assert_eq!(png.as_bytes(), testing_png().as_bytes());
+    }
+
+    #[test]
+    pub fn test_trailing_garbage() {
+        let mut bytes = testing_png().as_bytes();
+        bytes.extend_from_slice(b"garbage");
+
+        let (png, issues) = read_damaged(&bytes);
+        assert_eq!(png.unwrap().chunks().len(), 3);
+        assert!(matches!(
+            issues.as_slice(),
+            [ValidationIssue::UnreadableData { offset: 63, .. }]
+        ));
+    }
+
+    #[test]
+    pub fn test_from_bytes_lossy() {
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("ruSt", b"damaged"),
+            chunk("ruSt", b"intact"),
+            chunk("IEND", b""),
+        ]);
+        let mut bytes = png.as_bytes();
+        // A byte of the first ruSt chunk's data
+        bytes[8 + 25 + 8] ^= 1;
+
+        let (lossy, issues) = Png::from_bytes_lossy(&bytes);
+        let types: Vec<String> = lossy
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "ruSt", "IEND"]);
+        assert_eq!(lossy.chunks()[1].data(), b"intact");
+        assert!(matches!(
+            issues.as_slice(),
+            [ValidationIssue::SkippedData {
+                offset: 33,
+                length: 19,
+                ..
+            }]
+        ));
+
+        // A bogus length, garbage in the middle and no signature at all
+        bytes[8 + 25 + 3] = 0xff;
+        bytes.splice(8 + 25 + 19..8 + 25 + 19, *b"garbage");
+        let (lossy, issues) = Png::from_bytes_lossy(&bytes[8..]);
+        assert_eq!(lossy.chunks().len(), 3);
+        assert!(matches!(
+            issues.as_slice(),
+            [
+                ValidationIssue::MissingSignature,
+                ValidationIssue::SkippedData { offset: 25, length: 26, reason },
+            ] if reason.contains("length")
+        ));
+    }
+
+    // Many chunks, so rayon splits the work, with bad CRCs spread out so the order of the
+    // parallel results is checked too
+    #[cfg(feature = "parallel")]
+    #[test]
+    pub fn test_parallel_matches_sequential() {
+        let mut chunks = vec![chunk("IDAT", b"first")];
+        chunks.extend((0..5000).map(|i| chunk("IDAT", &u32::to_be_bytes(i))));
+        chunks.push(chunk("IHDR", &[0; 13]));
+        let mut bytes = Png::from_chunks(chunks).as_bytes();
+        let first_idat = 8 + Chunk::METADATA_BYTES + 5;
+        for index in [1, 17, 2500, 4999] {
+            // Last byte of the CRC of the chunk at this index
+            bytes[first_idat + index * (Chunk::METADATA_BYTES + 4) - 1] ^= 1;
+        }
+
+        let png = PngRef::new(&bytes).unwrap();
+        let chunks: Vec<ChunkRef> = png.chunks().map(Result::unwrap).collect();
+        let mut sequential: Vec<ValidationIssue> = chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, chunk)| check_crc(index, chunk))
+            .collect();
+        sequential.extend([
+            ValidationIssue::MisplacedIhdr { index: 5001 },
+            ValidationIssue::MissingIend,
+        ]);
+
+        assert_eq!(validate_chunks(&chunks), sequential);
+        assert_eq!(validate_bytes(&bytes), sequential);
+        let indices: Vec<usize> = sequential
+            .iter()
+            .filter_map(|issue| match issue {
+                ValidationIssue::BadCrc { index, .. } => Some(*index),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(indices, [1, 17, 2500, 4999]);
+    }
+
+    #[test]
+    pub fn test_missing_signature() {
+        assert_eq!(
+            validate_bytes(b"GIF89a"),
+            vec![ValidationIssue::MissingSignature]
+        );
+    }
+}
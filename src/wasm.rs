@@ -0,0 +1,94 @@
+//! Bindings for JavaScript, built with `wasm-pack build --target web -- --no-default-features
+//! --features wasm`. PNGs go in and come out as `Uint8Array`s, so nothing touches the disk.
+
+use wasm_bindgen::prelude::*;
+
+use crate::png::Png;
+
+/// Stores `data` under `chunkType` right before IEND, compressed and wrapped in an envelope as
+/// `pngme encode` stores it, and returns the new PNG
+#[wasm_bindgen(js_name = encodeBytes)]
+pub fn encode_bytes(png: &[u8], chunk_type: &str, data: &[u8]) -> Result<Vec<u8>, JsError> {
+    let mut png = Png::try_from(png)?;
+    png.encode_payload(chunk_type, data, true, None)?;
+    Ok(png.as_bytes())
+}
+
+/// Reads the bytes stored under `chunkType` by `encodeBytes` or `pngme encode`, joining them
+/// back together if they were split across several chunks
+#[wasm_bindgen(js_name = decodeBytes)]
+pub fn decode_bytes(png: &[u8], chunk_type: &str) -> Result<Vec<u8>, JsError> {
+    Ok(Png::try_from(png)?.decode_payload(chunk_type, None)?)
+}
+
+/// Lists the chunks of a PNG in file order
+#[wasm_bindgen(js_name = listChunks)]
+pub fn list_chunks(png: &[u8]) -> Result<Vec<ChunkSummary>, JsError> {
+    let chunks = Png::try_from(png)?
+        .chunks()
+        .iter()
+        .map(|chunk| ChunkSummary {
+            chunk_type: chunk.chunk_type().to_string(),
+            length: chunk.length(),
+            crc: chunk.crc(),
+        })
+        .collect();
+    Ok(chunks)
+}
+
+/// A chunk as seen from JavaScript, without its data
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkSummary {
+    chunk_type: String,
+    length: u32,
+    crc: u32,
+}
+
+#[wasm_bindgen]
+impl ChunkSummary {
+    /// The four letter chunk type, e.g. `"IHDR"`
+    #[wasm_bindgen(getter, js_name = chunkType)]
+    pub fn chunk_type(&self) -> String {
+        self.chunk_type.clone()
+    }
+
+    /// Number of bytes in the chunk's data field
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// The CRC stored in the file
+    #[wasm_bindgen(getter)]
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::envelope::Envelope;
+    use std::str::FromStr;
+
+    // Errors can only be built inside a JavaScript engine, so only the happy paths run here
+    #[test]
+    pub fn test_bindings() {
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+        .as_bytes();
+
+        let encoded = encode_bytes(&png, "ruSt", b"hello").unwrap();
+        assert_eq!(decode_bytes(&encoded, "ruSt").unwrap(), b"hello");
+        let chunks = list_chunks(&encoded).unwrap();
+        let types: Vec<String> = chunks.iter().map(ChunkSummary::chunk_type).collect();
+        assert_eq!(types, ["IHDR", "ruSt", "IEND"]);
+        let sealed = Envelope::seal(b"hello", None, true, None).unwrap();
+        assert_eq!(chunks[1].length() as usize, sealed.to_bytes().len());
+    }
+}